@@ -0,0 +1,80 @@
+// 命令行参数模块
+
+use std::path::PathBuf;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+#[derive(Parser)]
+#[command(name = "run_cat", version, about = "在系统托盘奔跑的小猫，速度随系统负载变化")]
+pub struct Cli {
+    /// 命令结束后保留结果图标和提示的秒数
+    #[arg(long, default_value_t = 5)]
+    pub linger: u64,
+
+    /// 把每次采样的结果录制到轨迹文件，可在「监控指标」中选择「轨迹回放」重放
+    #[arg(long, value_name = "FILE")]
+    pub record: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub action: Option<Action>,
+
+    /// 要运行的命令，小猫按该命令及其子进程的 CPU 占用奔跑，例如 `run_cat -- cargo build`
+    #[arg(last = true)]
+    pub command: Vec<String>,
+}
+
+#[derive(Subcommand)]
+pub enum Action {
+    /// 管理跑者包
+    Pack {
+        #[command(subcommand)]
+        command: PackCommand,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum PackCommand {
+    /// 把动画 GIF、APNG 或 PNG 精灵图转换为跑者包，不启动托盘
+    Import(ImportArgs),
+}
+
+#[derive(Args)]
+pub struct ImportArgs {
+    /// 输入的 GIF、APNG 或 PNG 精灵图
+    pub input: PathBuf,
+
+    /// 输出目录，默认为跑者包目录下以跑者名称命名的子目录
+    #[arg(short, long, value_name = "DIR")]
+    pub output: Option<PathBuf>,
+
+    /// 跑者名称，默认为输入文件名
+    #[arg(long)]
+    pub name: Option<String>,
+
+    /// 按精灵图导入，指定各帧的排列方向；不指定时没有动画的 PNG 按宽高比判断
+    #[arg(long, value_enum)]
+    pub sprite: Option<SpriteArg>,
+
+    /// 精灵图的帧数，默认按正方形帧计算
+    #[arg(long)]
+    pub frames: Option<usize>,
+
+    /// 精灵图每帧的显示时长（毫秒）
+    #[arg(long, default_value_t = 100)]
+    pub frame_ms: u32,
+
+    /// 保留原图的透明边距，默认裁掉所有帧共同的透明边距
+    #[arg(long)]
+    pub keep_margin: bool,
+
+    /// 覆盖已存在的输出目录
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum SpriteArg {
+    /// 各帧从左到右排列
+    Horizontal,
+    /// 各帧从上到下排列
+    Vertical,
+}
@@ -3,7 +3,9 @@
 pub const IDI_APP_ICON: u16 = 100; //logo
 pub const IDI_LIGHTCAT_0: u16 = 101; //首帧浅色图标
 pub const IDI_DARKCAT_0: u16 = 106; //首帧深色图标
-pub const FRAME_COUNT: usize = 5; // 猫咪动画的总帧数
+pub const BUILTIN_FRAME_COUNT: usize = 5; // 内置小猫动画的帧数，跑者包的帧数由其清单决定
+pub const IDI_STATUS_OK: u16 = 32516; // 系统预定义图标 IDI_INFORMATION，命令成功
+pub const IDI_STATUS_FAILED: u16 = 32513; // 系统预定义图标 IDI_ERROR，命令失败
 pub const WM_TRAYICON: u32 = winapi::um::winuser::WM_USER + 1; 
 pub const IDM_EXIT: u32 = 1001; // 退出菜单项
 pub const IDM_START_SYSTEM: u32 = 1002; // 开机自启菜单项
@@ -12,3 +14,12 @@ pub const IDM_SHOW_TIME: u32 = 1003; // 显示时间窗口菜单项
 pub const IDM_SKIN_AUTO: u32 = 1005; // 自动模式
 pub const IDM_SKIN_DARK: u32 = 1006; // 深色模式
 pub const IDM_SKIN_LIGHT: u32 = 1007; // 浅色模式
+pub const IDM_RUNNER_RELOAD: u32 = 1008; // 重新加载跑者包
+
+pub const IDM_METRIC_BASE: u32 = 1100; // 指标源菜单项起始ID，按 metrics::available_metrics() 的顺序递增
+pub const IDM_METRIC_LAST: u32 = 1199; // 指标源菜单项结束ID
+pub const IDM_RUNNER_BASE: u32 = 1300; // 跑者菜单项起始ID，首项为内置小猫，之后按 runner::list_packs() 的顺序递增
+pub const IDM_RUNNER_LAST: u32 = 1399; // 跑者菜单项结束ID
+pub const IDM_OPTION_BASE: u32 = 2000; // 选项子菜单（指标源选项、速度曲线、图标数值）菜单项起始ID，按各选项的顺序递增
+pub const IDM_OPTION_LAST: u32 = 2999; // 选项子菜单菜单项结束ID
+pub const MAX_OPTION_CHOICES: usize = 64; // 每个选项子菜单最多列出的选项数，如 CPU 模式中的各核心
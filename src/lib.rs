@@ -0,0 +1,4 @@
+// 跑者包的读取、导入和图像处理。这部分不依赖 Windows API，单独编译为库，
+// 可以在任意平台上用 cargo test --lib 测试；托盘程序通过 run_cat::pack 使用
+
+pub mod pack;
@@ -7,8 +7,15 @@ mod window;
 mod tray;
 mod updater;
 mod timer;
+mod metrics;
+mod cli;
+mod wrapper;
+mod process_tree;
+mod runner;
+mod speed;
 
 use std::{ptr::null_mut, sync::mpsc, time::Duration};
+use clap::Parser;
 use winapi::um::winuser::{PeekMessageW, TranslateMessage, DispatchMessageW, MSG, WM_QUIT, PM_REMOVE};
 use winapi::um::shellapi::NOTIFYICONDATAW;
 use winapi::shared::minwindef::HINSTANCE;
@@ -17,13 +24,45 @@ use constants::IDI_LIGHTCAT_0;
 use utils::get_module_handle;
 use window::register_class_and_create_window;
 use tray::{create_notify_icon_data, add_tray_icon, remove_tray_icon};
-use updater::start_updater_thread;
+use updater::{start_updater_thread, Snapshot};
+use runner::Runner;
 
 fn main() {
+    // 带参数启动时通常来自终端，附加到该终端以显示帮助、错误和子进程输出
+    if std::env::args_os().len() > 1 {
+        utils::attach_parent_console();
+    }
+    let cli = cli::Cli::parse();
+
+    if let Some(cli::Action::Pack { command: cli::PackCommand::Import(args) }) = &cli.action {
+        import_pack(args);
+        return;
+    }
+
+    let command = if cli.command.is_empty() {
+        None
+    } else {
+        match wrapper::WrappedCommand::spawn(&cli.command) {
+            Ok(command) => Some(command),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(127);
+            }
+        }
+    };
+
+    let recorder = cli.record.as_deref().map(|path| {
+        metrics::TraceRecorder::create(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        })
+    });
+
     let hinstance = get_module_handle();
     let (tx, rx) = mpsc::channel();
-    
-    start_updater_thread(tx);
+
+    metrics::start_push_listener();
+    start_updater_thread(tx, command, recorder, Duration::from_secs(cli.linger));
 
     let class_name_w = utils::to_wide_null(format!("RunCatClass{}", std::process::id()));
     register_class_and_create_window(hinstance, class_name_w.as_ptr()).unwrap_or_else(|e| {
@@ -36,21 +75,52 @@ fn main() {
     
     if !add_tray_icon(&mut nid) {
         eprintln!("Failed to add tray icon");
+        wrapper::terminate();
         return;
     }
 
     run_message_loop(&rx, &mut nid, hinstance);
     remove_tray_icon(&mut nid);
+
+    // 从托盘菜单退出时命令可能仍在运行
+    wrapper::terminate();
+    std::process::exit(wrapper::exit_code());
+}
+
+// 导入跑者包并输出结果，失败时以退出码 1 退出
+fn import_pack(args: &cli::ImportArgs) {
+    let options = runner::ImportOptions {
+        input: args.input.clone(),
+        output: args.output.clone(),
+        name: args.name.clone(),
+        sprite: args.sprite.map(|sprite| match sprite {
+            cli::SpriteArg::Horizontal => runner::SpriteLayout::Horizontal,
+            cli::SpriteArg::Vertical => runner::SpriteLayout::Vertical,
+        }),
+        frames: args.frames,
+        frame_ms: args.frame_ms,
+        keep_margin: args.keep_margin,
+        force: args.force,
+    };
+    match runner::import_pack(&options) {
+        Ok(path) => println!("已生成跑者包: {}", path.display()),
+        Err(e) => {
+            eprintln!("导入失败: {}", e);
+            std::process::exit(1);
+        }
+    }
 }
 
 // 运行主消息循环
 
 // # 参数
-// * `rx`: 消息通道的接收端，用于接收来自后台更新线程的CPU使用率数据
+// * `rx`: 消息通道的接收端，用于接收来自后台更新线程的快照
 // * `nid`: 可变的系统托盘图标数据结构，用于更新托盘图标的显示
-// * `_hinstance`: 应用程序实例句柄（当前未使用，保留用于未来扩展）
-fn run_message_loop(rx: &mpsc::Receiver<(u32, f32)>, nid: &mut NOTIFYICONDATAW, _hinstance: HINSTANCE) {
+// * `hinstance`: 应用程序实例句柄，用于加载内置小猫的图标资源
+fn run_message_loop(rx: &mpsc::Receiver<Snapshot>, nid: &mut NOTIFYICONDATAW, hinstance: HINSTANCE) {
     let mut msg: MSG = unsafe { std::mem::zeroed() };
+    let mut runner = Runner::from_settings(hinstance);
+    let mut generation = utils::settings_generation();
     
     'msg_loop: loop {
         while unsafe { PeekMessageW(&mut msg, null_mut(), 0, 0, PM_REMOVE) } != 0 {
@@ -64,35 +134,48 @@ fn run_message_loop(rx: &mpsc::Receiver<(u32, f32)>, nid: &mut NOTIFYICONDATAW,
             }
         }
 
-        while let Ok((res_id, cpu_usage)) = rx.try_recv() {
-            update_tray_from_cpu(nid, res_id as u16, cpu_usage);
+        // 菜单请求重新加载，或设置中的跑者包发生变化时重新读取跑者
+        let reload = runner::take_reload_request();
+        if reload || utils::settings_generation() != generation {
+            generation = utils::settings_generation();
+            if reload || runner.setting() != utils::get_runner_pack() {
+                runner = Runner::from_settings(hinstance);
+            } else {
+                runner.refresh_overlay();
+            }
+        }
+
+        while let Ok(snapshot) = rx.try_recv() {
+            update_tray_from_snapshot(nid, &mut runner, &snapshot);
         }
 
         match rx.recv_timeout(Duration::from_millis(250)) {
-            Ok((res_id, cpu_usage)) => update_tray_from_cpu(nid, res_id as u16, cpu_usage),
+            Ok(snapshot) => update_tray_from_snapshot(nid, &mut runner, &snapshot),
             Err(mpsc::RecvTimeoutError::Disconnected) => break 'msg_loop,
             Err(mpsc::RecvTimeoutError::Timeout) => {}
         }
     }
 }
 
-// 更新托盘图标和CPU信息显示
+// 按快照更新托盘图标和指标信息显示
 
 // # 参数
 // * `nid`: 可变的系统托盘图标数据结构，包含图标、提示文本等配置
-// * `res_id`: 要显示的图标资源ID，对应不同CPU使用率的猫咪动画帧
-// * `cpu_usage`: 当前的CPU使用率（百分比）
-fn update_tray_from_cpu(nid: &mut NOTIFYICONDATAW, res_id: u16, cpu_usage: f32) {
-    let hinstance = get_module_handle();
-    let icon = utils::load_icon(hinstance, res_id);
-    
-    nid.hIcon = icon;
+// * `runner`: 当前使用的跑者，提供各帧图标并按设置叠加驱动动画的指标值
+// * `snapshot`: 更新线程发送的快照，包含动画帧、配色和全部指标读数
+fn update_tray_from_snapshot(nid: &mut NOTIFYICONDATAW, runner: &mut Runner, snapshot: &Snapshot) {
+    window::set_latest_snapshot(snapshot);
+
+    let percent = snapshot.metrics.first().and_then(|reading| reading.percent);
+    nid.hIcon = match snapshot.status_icon() {
+        Some(res_id) => utils::load_icon(get_module_handle(), res_id),
+        None => runner.icon_with_value(snapshot.theme, snapshot.frame, percent),
+    };
+
+    let wide = utils::to_wide_null(snapshot.tooltip());
 
-    let tip = format!("CPU: {:.0}%", cpu_usage);
-    let wide = utils::to_wide_null(&tip);
-    
     nid.szTip = [0u16; 128];
-    
+
     for (i, &c) in wide.iter().take(127).enumerate() {
         nid.szTip[i] = c;
     }
@@ -0,0 +1,198 @@
+// 电池指标源：电量和放电功率
+//
+// Linux 读取 /sys/class/power_supply/* 中类型为 Battery 的设备，Windows 使用 GetSystemPowerStatus
+
+use super::{MetricSource, MetricState};
+
+// 统计的电池数值
+#[derive(Clone, Copy, PartialEq)]
+pub enum BatteryKind {
+    // 电量百分比
+    Charge,
+    // 放电功率（瓦）
+    Drain,
+}
+
+// 某一时刻的电池状态
+#[derive(Clone, Copy)]
+struct BatteryStatus {
+    capacity: f32,
+    charging: bool,
+    // 放电功率（瓦），无法获取时为 None
+    power_w: Option<f32>,
+    // 剩余可用时间（秒），无法获取时为 None
+    seconds_left: Option<u64>,
+}
+
+pub struct BatterySource {
+    kind: BatteryKind,
+    max_watts: f32,
+    last: Option<BatteryStatus>,
+}
+
+impl BatterySource {
+    pub fn new(kind: BatteryKind, max_watts: u32) -> Self {
+        BatterySource {
+            kind,
+            max_watts: max_watts.max(1) as f32,
+            last: None,
+        }
+    }
+
+    // 按设置创建
+    pub fn from_settings(kind: BatteryKind) -> Self {
+        BatterySource::new(kind, crate::utils::get_battery_max_watts())
+    }
+}
+
+impl MetricSource for BatterySource {
+    fn name(&self) -> &str {
+        match self.kind {
+            BatteryKind::Charge => "电量",
+            BatteryKind::Drain => "放电功率",
+        }
+    }
+
+    fn unit(&self) -> &str {
+        match self.kind {
+            BatteryKind::Charge => "%",
+            BatteryKind::Drain => "W",
+        }
+    }
+
+    fn range(&self) -> (f32, f32) {
+        match self.kind {
+            BatteryKind::Charge => (0.0, 100.0),
+            BatteryKind::Drain => (0.0, self.max_watts),
+        }
+    }
+
+    fn sample(&mut self) -> Result<f32, String> {
+        let status = read_battery_status()?;
+        self.last = Some(status);
+
+        match self.kind {
+            BatteryKind::Charge => Ok(status.capacity),
+            BatteryKind::Drain if status.charging => Ok(0.0),
+            BatteryKind::Drain => status.power_w.ok_or_else(|| "无法获取放电功率".to_string()),
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        let status = self.last?;
+        let mut detail = if status.charging {
+            format!("充电中，电量 {:.0}%", status.capacity)
+        } else {
+            match status.seconds_left {
+                Some(secs) => format!("电量 {:.0}%，剩余 {} 时 {:02} 分", status.capacity, secs / 3600, secs % 3600 / 60),
+                None => format!("电量 {:.0}%", status.capacity),
+            }
+        };
+        if let Some(power) = status.power_w {
+            detail.push_str(&format!("，{:.1} W", power));
+        }
+        Some(detail)
+    }
+
+    fn state(&self) -> MetricState {
+        match self.last {
+            Some(status) if status.charging => MetricState::Charging,
+            _ => MetricState::Normal,
+        }
+    }
+}
+
+// 汇总 /sys/class/power_supply 下所有电池；剩余时间按剩余能量除以放电功率计算
+#[cfg(target_os = "linux")]
+fn read_battery_status() -> Result<BatteryStatus, String> {
+    let entries = std::fs::read_dir("/sys/class/power_supply")
+        .map_err(|_| "无法读取 /sys/class/power_supply".to_string())?;
+
+    let mut found = 0;
+    let mut capacity_sum = 0.0f32;
+    let mut charging = false;
+    let mut power_uw: Option<f64> = None;
+    let mut energy_uwh: Option<f64> = None;
+
+    for entry in entries.flatten() {
+        let dir = entry.path();
+        let read = |name: &str| std::fs::read_to_string(dir.join(name)).map(|s| s.trim().to_string()).ok();
+        let read_num = |name: &str| read(name).and_then(|s| s.parse::<f64>().ok());
+
+        if read("type").as_deref() != Some("Battery") {
+            continue;
+        }
+        found += 1;
+
+        capacity_sum += read_num("capacity").unwrap_or(0.0) as f32;
+        if read("status").as_deref() == Some("Charging") {
+            charging = true;
+        }
+
+        // 优先使用 power_now（微瓦），否则用 current_now（微安）乘以 voltage_now（微伏）
+        let power = read_num("power_now").or_else(|| {
+            let current = read_num("current_now")?;
+            let voltage = read_num("voltage_now")?;
+            Some(current * voltage / 1_000_000.0)
+        });
+        if let Some(power) = power {
+            *power_uw.get_or_insert(0.0) += power.abs();
+        }
+
+        let energy = read_num("energy_now").or_else(|| {
+            let charge = read_num("charge_now")?;
+            let voltage = read_num("voltage_now")?;
+            Some(charge * voltage / 1_000_000.0)
+        });
+        if let Some(energy) = energy {
+            *energy_uwh.get_or_insert(0.0) += energy;
+        }
+    }
+
+    if found == 0 {
+        return Err("未找到电池".to_string());
+    }
+
+    let seconds_left = match (energy_uwh, power_uw) {
+        (Some(energy), Some(power)) if !charging && power > 0.0 => Some((energy / power * 3600.0) as u64),
+        _ => None,
+    };
+
+    Ok(BatteryStatus {
+        capacity: capacity_sum / found as f32,
+        charging,
+        power_w: power_uw.map(|p| (p / 1_000_000.0) as f32),
+        seconds_left,
+    })
+}
+
+// GetSystemPowerStatus 不提供放电功率，剩余时间由系统估算
+#[cfg(windows)]
+fn read_battery_status() -> Result<BatteryStatus, String> {
+    use winapi::um::winbase::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    let mut status: SYSTEM_POWER_STATUS = unsafe { std::mem::zeroed() };
+    if unsafe { GetSystemPowerStatus(&mut status) } == 0 {
+        return Err("无法获取电源状态".to_string());
+    }
+
+    // BatteryFlag: 8 充电中，128 没有电池，255 未知
+    if status.BatteryFlag & 128 != 0 || status.BatteryLifePercent == 255 {
+        return Err("未找到电池".to_string());
+    }
+
+    Ok(BatteryStatus {
+        capacity: status.BatteryLifePercent as f32,
+        charging: status.BatteryFlag & 8 != 0,
+        power_w: None,
+        seconds_left: match status.BatteryLifeTime {
+            u32::MAX => None,
+            secs => Some(secs as u64),
+        },
+    })
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn read_battery_status() -> Result<BatteryStatus, String> {
+    Err("当前系统不支持电池指标".to_string())
+}
@@ -0,0 +1,183 @@
+// cgroup v2 指标源：按 cgroup 配额统计 CPU 和内存占用，适用于容器和 systemd slice
+//
+// 读取 cgroup 目录下的 cpu.stat、cpu.max、memory.current 和 memory.max；
+// 未配置路径时根据 /proc/self/cgroup 自动定位当前进程所在的 cgroup
+
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use sysinfo::{System, SystemExt};
+
+use super::MetricSource;
+
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+// 混合模式（v1 与 v2 共存）下 cgroup v2 挂载在该目录
+const CGROUP_UNIFIED_ROOT: &str = "/sys/fs/cgroup/unified";
+const GIB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+// 统计的资源类型
+#[derive(Clone, Copy, PartialEq)]
+pub enum CgroupResource {
+    Cpu,
+    Memory,
+}
+
+pub struct CgroupSource {
+    resource: CgroupResource,
+    path: Result<PathBuf, String>,
+    last_usage_usec: Option<u64>,
+    last_sample: Instant,
+    // 最近一次采样的 (已用, 上限)：CPU 为核数，内存为字节
+    last: Option<(f64, f64)>,
+    // 未设置配额时使用的主机逻辑核数和物理内存（字节），创建时读取一次
+    host_cores: f64,
+    host_memory: u64,
+}
+
+impl CgroupSource {
+    pub fn new(resource: CgroupResource, path: &str) -> Self {
+        let mut system = System::new();
+        system.refresh_memory();
+
+        CgroupSource {
+            resource,
+            path: resolve_cgroup_path(path),
+            last_usage_usec: None,
+            last_sample: Instant::now(),
+            last: None,
+            host_cores: super::cpu::logical_core_count().max(1) as f64,
+            host_memory: system.total_memory(),
+        }
+    }
+
+    // 按设置创建
+    pub fn from_settings(resource: CgroupResource) -> Self {
+        CgroupSource::new(resource, &crate::utils::get_cgroup_path())
+    }
+
+    fn sample_cpu(&mut self, dir: &Path) -> Result<f32, String> {
+        let stat = read_cgroup_file(dir, "cpu.stat")?;
+        let usage_usec = stat
+            .lines()
+            .find_map(|line| line.strip_prefix("usage_usec "))
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .ok_or_else(|| "cpu.stat 中没有 usage_usec".to_string())?;
+
+        let quota_cores = match read_cgroup_file(dir, "cpu.max") {
+            Ok(max) => parse_cpu_max(&max).unwrap_or(self.host_cores),
+            // 根 cgroup 没有 cpu.max
+            Err(_) => self.host_cores,
+        };
+
+        let elapsed_usec = self.last_sample.elapsed().as_micros().max(1) as f64;
+        self.last_sample = Instant::now();
+
+        let used_cores = match self.last_usage_usec.replace(usage_usec) {
+            Some(prev) => usage_usec.saturating_sub(prev) as f64 / elapsed_usec,
+            None => 0.0,
+        };
+        self.last = Some((used_cores, quota_cores));
+
+        Ok((used_cores / quota_cores * 100.0) as f32)
+    }
+
+    fn sample_memory(&mut self, dir: &Path) -> Result<f32, String> {
+        let current = read_cgroup_file(dir, "memory.current")?
+            .trim()
+            .parse::<u64>()
+            .map_err(|_| "memory.current 格式无法识别".to_string())?;
+
+        let limit = match read_cgroup_file(dir, "memory.max") {
+            Ok(max) if max.trim() != "max" => max
+                .trim()
+                .parse::<u64>()
+                .map_err(|_| "memory.max 格式无法识别".to_string())?,
+            // 未设置上限时按主机物理内存计算
+            _ => self.host_memory,
+        };
+        if limit == 0 {
+            return Err("内存上限为 0".to_string());
+        }
+        self.last = Some((current as f64, limit as f64));
+
+        Ok((current as f64 / limit as f64 * 100.0) as f32)
+    }
+}
+
+impl MetricSource for CgroupSource {
+    fn name(&self) -> &str {
+        match self.resource {
+            CgroupResource::Cpu => "cgroup CPU",
+            CgroupResource::Memory => "cgroup 内存",
+        }
+    }
+
+    fn unit(&self) -> &str {
+        "%"
+    }
+
+    fn range(&self) -> (f32, f32) {
+        (0.0, 100.0)
+    }
+
+    fn sample(&mut self) -> Result<f32, String> {
+        let dir = self.path.clone()?;
+        match self.resource {
+            CgroupResource::Cpu => self.sample_cpu(&dir),
+            CgroupResource::Memory => self.sample_memory(&dir),
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        let (used, limit) = self.last?;
+        let dir = self.path.as_ref().ok()?.display();
+        Some(match self.resource {
+            CgroupResource::Cpu => format!("{:.2}/{:.2} 核\n{}", used, limit, dir),
+            CgroupResource::Memory => format!("{:.2}/{:.2} GB\n{}", used / GIB, limit / GIB, dir),
+        })
+    }
+}
+
+// 解析 cgroup 路径：绝对路径直接使用，相对路径基于 cgroup v2 挂载点，为空时自动检测
+fn resolve_cgroup_path(path: &str) -> Result<PathBuf, String> {
+    let path = path.trim();
+    let root = if Path::new(CGROUP_ROOT).join("cgroup.controllers").exists() {
+        CGROUP_ROOT
+    } else {
+        CGROUP_UNIFIED_ROOT
+    };
+
+    let dir = if path.is_empty() {
+        let content = std::fs::read_to_string("/proc/self/cgroup")
+            .map_err(|_| "无法读取 /proc/self/cgroup".to_string())?;
+        // cgroup v2 的记录形如 "0::/user.slice/..."
+        let relative = content
+            .lines()
+            .find_map(|line| line.strip_prefix("0::"))
+            .ok_or_else(|| "未找到 cgroup v2 层级".to_string())?;
+        PathBuf::from(root).join(relative.trim_start_matches('/'))
+    } else if path.starts_with(CGROUP_ROOT) {
+        PathBuf::from(path)
+    } else {
+        PathBuf::from(root).join(path.trim_start_matches('/'))
+    };
+
+    if !dir.join("cgroup.controllers").exists() {
+        return Err(format!("{} 不是 cgroup v2 目录", dir.display()));
+    }
+    Ok(dir)
+}
+
+fn read_cgroup_file(dir: &Path, name: &str) -> Result<String, String> {
+    std::fs::read_to_string(dir.join(name)).map_err(|_| format!("无法读取 {}", name))
+}
+
+// 解析 cpu.max（"配额 周期" 或 "max 周期"），返回可用核数；不限额时返回 None
+fn parse_cpu_max(content: &str) -> Option<f64> {
+    let mut parts = content.split_whitespace();
+    let quota = parts.next()?.parse::<f64>().ok()?;
+    let period = parts.next()?.parse::<f64>().ok()?;
+    if period <= 0.0 {
+        return None;
+    }
+    Some(quota / period)
+}
@@ -0,0 +1,392 @@
+// 外部命令指标源：在后台线程中运行用户配置的命令，从标准输出中解析数值。
+// 采样不等待命令结束，返回最近一次结束的命令的结果；上一次命令结束后的下一次采样再次运行命令
+//
+// 输出解析方式（CommandParse）：
+//   空或 number      输出中的第一个数字
+//   key:NAME         形如 NAME=值 或 NAME: 值 的行
+//   json:.a.b[0]     把输出解析为 JSON，按路径取值
+// 命令失败或超时后按 2、4、8……最长 60 秒的间隔退避，期间不再运行命令，提示中显示最近一次的错误
+
+use std::io::Read;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
+use regex::Regex;
+use serde_json::Value;
+
+use super::MetricSource;
+use crate::process_tree::ProcessTree;
+
+const MAX_BACKOFF_SECS: u64 = 60;
+
+// JSON 路径中的一段
+enum PathStep {
+    Key(String),
+    Index(usize),
+}
+
+// 标准输出的解析方式
+enum OutputParser {
+    Number,
+    Key(String),
+    Json(Vec<PathStep>),
+}
+
+impl OutputParser {
+    fn parse(spec: &str) -> Result<OutputParser, String> {
+        let spec = spec.trim();
+        if spec.is_empty() || spec == "number" {
+            Ok(OutputParser::Number)
+        } else if let Some(key) = spec.strip_prefix("key:") {
+            match key.trim() {
+                "" => Err("key: 后缺少键名".to_string()),
+                key => Ok(OutputParser::Key(key.to_string())),
+            }
+        } else if let Some(path) = spec.strip_prefix("json:") {
+            parse_json_path(path.trim()).map(OutputParser::Json)
+        } else {
+            Err(format!("无法识别的解析方式: {}", spec))
+        }
+    }
+
+    fn extract(&self, output: &str) -> Result<f32, String> {
+        match self {
+            OutputParser::Number => first_number(output).ok_or_else(|| "输出中没有数字".to_string()),
+            OutputParser::Key(key) => output
+                .lines()
+                .find_map(|line| {
+                    let rest = line.trim().strip_prefix(key.as_str())?.trim_start();
+                    rest.strip_prefix('=').or_else(|| rest.strip_prefix(':'))
+                })
+                .ok_or_else(|| format!("输出中没有 {}", key))
+                .and_then(|value| first_number(value).ok_or_else(|| format!("{} 的值不是数字", key))),
+            OutputParser::Json(path) => {
+                let root: Value = serde_json::from_str(output).map_err(|e| format!("输出不是有效的 JSON: {}", e))?;
+                let value = path
+                    .iter()
+                    .try_fold(&root, |value, step| match step {
+                        PathStep::Key(key) => value.get(key.as_str()),
+                        PathStep::Index(index) => value.get(*index),
+                    })
+                    .ok_or_else(|| "JSON 中没有该路径".to_string())?;
+                match value {
+                    Value::Number(n) => n.as_f64().map(|v| v as f32).ok_or_else(|| "JSON 数值无效".to_string()),
+                    Value::String(s) => first_number(s).ok_or_else(|| "JSON 字符串不是数字".to_string()),
+                    Value::Bool(b) => Ok(if *b { 1.0 } else { 0.0 }),
+                    _ => Err("JSON 路径指向的不是数值".to_string()),
+                }
+            }
+        }
+    }
+}
+
+// 解析 jq 风格的路径，如 .jobs[0].progress；单独的 . 表示整个输出
+fn parse_json_path(path: &str) -> Result<Vec<PathStep>, String> {
+    let invalid = || format!("无效的 JSON 路径: {}", path);
+    if !path.starts_with('.') {
+        return Err(invalid());
+    }
+
+    let mut steps = Vec::new();
+    let mut rest = path;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('[') {
+            let (index, after) = after.split_once(']').ok_or_else(invalid)?;
+            steps.push(PathStep::Index(index.trim().parse().map_err(|_| invalid())?));
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('.') {
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            if end > 0 {
+                steps.push(PathStep::Key(after[..end].to_string()));
+            } else if !after.is_empty() && !after.starts_with('[') {
+                return Err(invalid());
+            }
+            rest = &after[end..];
+        } else {
+            return Err(invalid());
+        }
+    }
+    Ok(steps)
+}
+
+// 取文本中的第一个数字，支持负数、小数和科学计数法
+fn first_number(text: &str) -> Option<f32> {
+    static NUMBER: OnceLock<Regex> = OnceLock::new();
+    let re = NUMBER.get_or_init(|| Regex::new(r"-?\d+(\.\d+)?([eE][-+]?\d+)?").unwrap());
+    re.find(text)?.as_str().parse().ok()
+}
+
+pub struct CommandSource {
+    command: String,
+    parser: Result<OutputParser, String>,
+    range: (f32, f32),
+    timeout: Duration,
+    // 连续失败次数和退避结束时间
+    failures: u32,
+    retry_at: Option<Instant>,
+    last_error: String,
+    // 正在后台运行的命令，结束时通过通道发送标准输出或错误
+    running: Option<mpsc::Receiver<Result<String, String>>>,
+    // 最近一次结束的命令的结果
+    last: Option<Result<f32, String>>,
+}
+
+impl CommandSource {
+    pub fn new(command: &str, parse: &str, range: (f32, f32), timeout: Duration) -> Self {
+        CommandSource {
+            command: command.trim().to_string(),
+            parser: OutputParser::parse(parse),
+            range,
+            timeout,
+            failures: 0,
+            retry_at: None,
+            last_error: String::new(),
+            running: None,
+            last: None,
+        }
+    }
+
+    // 记录一次命令的结果，失败时开始退避
+    fn finish(&mut self, result: Result<f32, String>) {
+        match &result {
+            Ok(_) => {
+                self.failures = 0;
+                self.retry_at = None;
+            }
+            Err(e) => {
+                self.failures += 1;
+                let backoff = 2u64.saturating_pow(self.failures).min(MAX_BACKOFF_SECS);
+                self.retry_at = Some(Instant::now() + Duration::from_secs(backoff));
+                self.last_error = e.clone();
+            }
+        }
+        self.last = Some(result);
+    }
+
+    // 按设置创建
+    pub fn from_settings() -> Self {
+        CommandSource::new(
+            &crate::utils::get_command_line(),
+            &crate::utils::get_command_parse(),
+            (crate::utils::get_command_min(), crate::utils::get_command_max()),
+            Duration::from_millis(crate::utils::get_command_timeout_ms() as u64),
+        )
+    }
+}
+
+impl MetricSource for CommandSource {
+    fn name(&self) -> &str {
+        "外部命令"
+    }
+
+    fn unit(&self) -> &str {
+        ""
+    }
+
+    fn range(&self) -> (f32, f32) {
+        self.range
+    }
+
+    fn sample(&mut self) -> Result<f32, String> {
+        if self.command.is_empty() {
+            return Err("未设置命令".to_string());
+        }
+        // 解析方式设置错误时不运行命令，也不退避
+        let parser = self.parser.as_ref().map_err(|e| e.clone())?;
+
+        if let Some(running) = &self.running {
+            let result = match running.try_recv() {
+                Ok(result) => Some(result),
+                Err(mpsc::TryRecvError::Empty) => None,
+                Err(mpsc::TryRecvError::Disconnected) => Some(Err("命令线程意外结束".to_string())),
+            };
+            if let Some(result) = result {
+                self.running = None;
+                let value = result.and_then(|output| parser.extract(&output));
+                self.finish(value);
+            }
+        }
+
+        // 没有命令在运行且不在退避期间时启动下一次命令
+        let now = Instant::now();
+        let backing_off = self.retry_at.is_some_and(|retry_at| now < retry_at);
+        if self.running.is_none() && !backing_off {
+            let (tx, rx) = mpsc::channel();
+            let (command, timeout) = (self.command.clone(), self.timeout);
+            thread::spawn(move || {
+                let _ = tx.send(run_command(&command, timeout));
+            });
+            self.running = Some(rx);
+        }
+
+        match self.retry_at {
+            Some(retry_at) if now < retry_at => {
+                let secs = (retry_at - now).as_secs() + 1;
+                Err(format!("{}（{} 秒后重试）", self.last_error, secs))
+            }
+            _ => self.last.clone().unwrap_or_else(|| Err("等待命令结果".to_string())),
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        if self.command.is_empty() {
+            return None;
+        }
+        Some(format!("$ {}", self.command))
+    }
+}
+
+// 通过系统 shell 运行命令，超时后结束命令及其启动的全部进程；返回标准输出
+fn run_command(command: &str, timeout: Duration) -> Result<String, String> {
+    let (mut child, tree) = ProcessTree::spawn(
+        shell_command(command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped()),
+    )
+    .map_err(|e| format!("无法启动命令: {}", e))?;
+
+    // 在单独的线程中读取输出，避免输出较多时管道写满导致命令阻塞
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+
+    let deadline = Instant::now() + timeout;
+    let timed_out = |child: &mut Child| {
+        tree.kill(child);
+        let _ = child.wait();
+        format!("命令超时（{:.1} 秒）", timeout.as_secs_f32())
+    };
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() >= deadline => return Err(timed_out(&mut child)),
+            Ok(None) => thread::sleep(Duration::from_millis(20)),
+            Err(e) => return Err(format!("等待命令失败: {}", e)),
+        }
+    };
+
+    // 命令启动的后台进程可能继承了管道，命令退出后输出仍不结束，读取同样受超时限制
+    let stdout = match stdout.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+        Ok(text) => text,
+        Err(_) => return Err(timed_out(&mut child)),
+    };
+    if !status.success() {
+        let stderr = stderr.recv_timeout(deadline.saturating_duration_since(Instant::now())).unwrap_or_default();
+        let code = status.code().map(|c| c.to_string()).unwrap_or_else(|| "?".to_string());
+        return Err(match stderr.lines().find(|l| !l.trim().is_empty()) {
+            Some(line) => format!("命令退出码 {}: {}", code, line.trim()),
+            None => format!("命令退出码 {}", code),
+        });
+    }
+    Ok(stdout)
+}
+
+// 在单独的线程中读取管道直到结束，读完后通过通道发送全部内容
+fn read_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> mpsc::Receiver<String> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut text = String::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_string(&mut text);
+        }
+        let _ = tx.send(text);
+    });
+    rx
+}
+
+#[cfg(windows)]
+fn shell_command(command: &str) -> Command {
+    use std::os::windows::process::CommandExt;
+    // 不为命令弹出控制台窗口
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    let mut cmd = Command::new("cmd");
+    cmd.args(["/C", command]).creation_flags(CREATE_NO_WINDOW);
+    cmd
+}
+
+#[cfg(not(windows))]
+fn shell_command(command: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.args(["-c", command]);
+    cmd
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extract(spec: &str, output: &str) -> Result<f32, String> {
+        OutputParser::parse(spec)?.extract(output)
+    }
+
+    #[test]
+    fn first_number_in_output() {
+        assert_eq!(extract("", "load: 42.5%"), Ok(42.5));
+        assert_eq!(extract("number", "-3 items"), Ok(-3.0));
+        assert_eq!(extract("number", "1.5e2\n7"), Ok(150.0));
+        assert_eq!(extract("number", "none"), Err("输出中没有数字".to_string()));
+    }
+
+    #[test]
+    fn key_lines() {
+        let output = "queue_length: 12\nprogress = 0.75\n";
+        assert_eq!(extract("key:progress", output), Ok(0.75));
+        assert_eq!(extract("key: queue_length", output), Ok(12.0));
+        assert_eq!(extract("key:missing", output), Err("输出中没有 missing".to_string()));
+        assert_eq!(extract("key:state", "state=idle"), Err("state 的值不是数字".to_string()));
+        assert!(OutputParser::parse("key:").is_err());
+    }
+
+    #[test]
+    fn json_paths() {
+        let output = r#"{"jobs": [{"progress": 40}, {"progress": "55%"}], "ok": true, "name": "x"}"#;
+        assert_eq!(extract("json:.jobs[0].progress", output), Ok(40.0));
+        assert_eq!(extract("json:.jobs[1].progress", output), Ok(55.0));
+        assert_eq!(extract("json:.ok", output), Ok(1.0));
+        assert_eq!(extract("json:.", "12"), Ok(12.0));
+        assert_eq!(extract("json:.jobs[2].progress", output), Err("JSON 中没有该路径".to_string()));
+        assert_eq!(extract("json:.jobs", output), Err("JSON 路径指向的不是数值".to_string()));
+        assert!(extract("json:.a", "not json").is_err());
+    }
+
+    #[test]
+    fn invalid_specs() {
+        assert!(OutputParser::parse("json:jobs").is_err());
+        assert!(OutputParser::parse("json:.a[x]").is_err());
+        assert!(OutputParser::parse("json:.a..b").is_err());
+        assert!(OutputParser::parse("regex:.*").is_err());
+    }
+
+    // 等待后台命令结束并再次采样
+    #[cfg(unix)]
+    fn sample_after(source: &mut CommandSource, wait: Duration) -> Result<f32, String> {
+        thread::sleep(wait);
+        source.sample()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sampling_does_not_wait_for_the_command() {
+        let mut source = CommandSource::new("sleep 0.5; echo 42", "", (0.0, 100.0), Duration::from_secs(5));
+        let started = Instant::now();
+        assert_eq!(source.sample(), Err("等待命令结果".to_string()));
+        assert_eq!(source.sample(), Err("等待命令结果".to_string()));
+        assert!(started.elapsed() < Duration::from_millis(300));
+        assert_eq!(sample_after(&mut source, Duration::from_millis(800)), Ok(42.0));
+        // 再次运行期间返回上一次的结果
+        assert_eq!(source.sample(), Ok(42.0));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn failures_back_off() {
+        let mut source = CommandSource::new("exit 3", "", (0.0, 100.0), Duration::from_secs(5));
+        assert!(source.sample().is_err());
+        let error = sample_after(&mut source, Duration::from_millis(300)).unwrap_err();
+        assert!(error.starts_with("命令退出码 3（"), "{}", error);
+        assert!(source.running.is_none());
+    }
+}
@@ -0,0 +1,194 @@
+// 综合指标源：用表达式组合多个指标，例如 max(cpu, mem) 或 if(battery < 20, 100, cpu)
+//
+// 表达式中的每个指标都按自身取值范围换算成 0-100 后代入，结果同样按 0-100 换算成动画速度
+
+use super::expr::Expr;
+use super::MetricSource;
+
+// 表达式中可用的简写名称：(简写, 指标 id)
+const ALIASES: &[(&str, &str)] = &[
+    ("mem", super::METRIC_MEMORY),
+    ("io", super::METRIC_DISK),
+    ("net", super::METRIC_NETWORK),
+    ("temp", super::METRIC_THERMAL),
+];
+
+pub struct CompositeSource {
+    text: String,
+    expr: Result<Expr, String>,
+    // 与 Expr::variables 一一对应的指标源
+    inputs: Vec<Box<dyn MetricSource>>,
+    values: Vec<f32>,
+}
+
+impl CompositeSource {
+    // 解析表达式并创建其中引用的指标源；表达式无效时输出带位置标记的错误，采样时返回该错误
+    pub fn new(text: &str) -> Self {
+        let expr = Expr::parse(text, &resolve_metric).map_err(|e| {
+            eprintln!("综合指标表达式无效: {}", e.display(text));
+            format!("表达式{}", e)
+        });
+        let inputs = match &expr {
+            Ok(expr) => expr
+                .variables()
+                .iter()
+                .filter_map(|id| super::create_source(id))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+
+        CompositeSource {
+            text: text.trim().to_string(),
+            expr,
+            inputs,
+            values: Vec::new(),
+        }
+    }
+
+    // 按设置创建
+    pub fn from_settings() -> Self {
+        CompositeSource::new(&crate::utils::get_composite_expr())
+    }
+}
+
+impl MetricSource for CompositeSource {
+    fn name(&self) -> &str {
+        "综合指标"
+    }
+
+    fn unit(&self) -> &str {
+        "%"
+    }
+
+    fn range(&self) -> (f32, f32) {
+        (0.0, 100.0)
+    }
+
+    fn sample(&mut self) -> Result<f32, String> {
+        let expr = self.expr.as_ref().map_err(|e| e.clone())?;
+
+        // 每次都采样全部输入，保证按差值计算的指标源在条件切换后数值连续。
+        // 采样失败的输入按 NaN 代入，例如没有电池时 if(battery < 20, 100, cpu) 仍按 cpu 计算
+        self.values.clear();
+        let mut failure = None;
+        for (id, input) in expr.variables().iter().zip(self.inputs.iter_mut()) {
+            match input.sample() {
+                Ok(value) => self.values.push(super::normalize(value, input.range())),
+                Err(e) => {
+                    failure.get_or_insert_with(|| format!("{}: {}", id, e));
+                    self.values.push(f32::NAN);
+                }
+            }
+        }
+
+        let result = expr.eval(&self.values);
+        if !result.is_finite() {
+            // 结果无效通常是因为用到了采样失败的输入，此时显示该输入的错误
+            return Err(failure.unwrap_or_else(|| "表达式结果无效".to_string()));
+        }
+        Ok(result)
+    }
+
+    fn detail(&self) -> Option<String> {
+        let expr = self.expr.as_ref().ok()?;
+        let values: Vec<String> = expr
+            .variables()
+            .iter()
+            .zip(&self.values)
+            .map(|(id, value)| {
+                if value.is_nan() {
+                    format!("{} 不可用", id)
+                } else {
+                    format!("{} {:.0}%", id, value)
+                }
+            })
+            .collect();
+        Some(format!("{}\n{}", self.text, values.join("，")))
+    }
+}
+
+// 把表达式中的名称解析为指标 id：内置指标 id 或简写，综合指标本身不能被引用
+fn resolve_metric(name: &str) -> Option<String> {
+    let id = ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map(|(_, id)| *id)
+        .unwrap_or(name);
+
+    if id == super::METRIC_COMPOSITE {
+        return None;
+    }
+    super::BUILTIN_SOURCES
+        .iter()
+        .find(|(source_id, _, _)| *source_id == id)
+        .map(|(source_id, _, _)| source_id.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 返回固定结果的指标源
+    struct Fixed(Result<f32, String>);
+
+    impl MetricSource for Fixed {
+        fn name(&self) -> &str {
+            "fixed"
+        }
+
+        fn unit(&self) -> &str {
+            "%"
+        }
+
+        fn range(&self) -> (f32, f32) {
+            (0.0, 100.0)
+        }
+
+        fn sample(&mut self) -> Result<f32, String> {
+            self.0.clone()
+        }
+    }
+
+    fn composite(text: &str, inputs: Vec<Result<f32, String>>) -> CompositeSource {
+        let expr = Expr::parse(text, &resolve_metric).map_err(|e| e.to_string());
+        CompositeSource {
+            text: text.to_string(),
+            expr,
+            inputs: inputs.into_iter().map(|r| Box::new(Fixed(r)) as Box<dyn MetricSource>).collect(),
+            values: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn aliases_resolve_to_metric_ids() {
+        assert_eq!(resolve_metric("mem").as_deref(), Some(super::super::METRIC_MEMORY));
+        assert_eq!(resolve_metric("cpu").as_deref(), Some("cpu"));
+        assert_eq!(resolve_metric("composite"), None);
+        assert_eq!(resolve_metric("foo"), None);
+    }
+
+    #[test]
+    fn composite_cannot_reference_itself() {
+        let source = composite("max(cpu, composite)", Vec::new());
+        assert_eq!(source.expr.err().unwrap(), "第 10 列: 未知的指标: composite");
+    }
+
+    #[test]
+    fn unavailable_input_skips_untaken_branch() {
+        let mut source = composite("if(battery < 20, 100, cpu)", vec![Err("未找到电池".to_string()), Ok(50.0)]);
+        assert_eq!(source.sample(), Ok(50.0));
+        assert_eq!(source.detail().unwrap(), "if(battery < 20, 100, cpu)\nbattery 不可用，cpu 50%");
+    }
+
+    #[test]
+    fn unavailable_input_in_result_reports_its_error() {
+        let mut source = composite("avg(battery, cpu)", vec![Err("未找到电池".to_string()), Ok(30.0)]);
+        assert_eq!(source.sample(), Err("battery: 未找到电池".to_string()));
+    }
+
+    #[test]
+    fn division_by_zero_is_an_error() {
+        let mut source = composite("cpu / 0", vec![Ok(30.0)]);
+        assert_eq!(source.sample(), Err("表达式结果无效".to_string()));
+    }
+}
@@ -0,0 +1,138 @@
+// CPU 指标源：支持全部核心平均、最繁忙核心、最繁忙 N 个核心平均和指定核心
+
+use sysinfo::{CpuExt, System, SystemExt};
+
+use super::MetricSource;
+
+// CPU 统计方式，设置中以字符串保存：average、max、top:N、core:N
+#[derive(Clone, Copy, PartialEq)]
+pub enum CpuMode {
+    Average,
+    Max,
+    TopMean(usize),
+    Core(usize),
+}
+
+impl CpuMode {
+    pub fn parse(s: &str) -> CpuMode {
+        let s = s.trim();
+        if s == "max" {
+            return CpuMode::Max;
+        }
+        if let Some(n) = s.strip_prefix("top:").and_then(|n| n.parse::<usize>().ok()) {
+            return CpuMode::TopMean(n.max(1));
+        }
+        if let Some(i) = s.strip_prefix("core:").and_then(|i| i.parse::<usize>().ok()) {
+            return CpuMode::Core(i);
+        }
+        CpuMode::Average
+    }
+
+    pub fn to_setting(self) -> String {
+        match self {
+            CpuMode::Average => "average".to_string(),
+            CpuMode::Max => "max".to_string(),
+            CpuMode::TopMean(n) => format!("top:{}", n),
+            CpuMode::Core(i) => format!("core:{}", i),
+        }
+    }
+
+    pub fn label(self) -> String {
+        match self {
+            CpuMode::Average => "全部核心平均".to_string(),
+            CpuMode::Max => "最繁忙核心".to_string(),
+            CpuMode::TopMean(n) => format!("最繁忙 {} 核平均", n),
+            CpuMode::Core(i) => format!("核心 {}", i),
+        }
+    }
+}
+
+pub struct CpuSource {
+    system: System,
+    mode: CpuMode,
+    name: String,
+    // 最近一次采样中最繁忙的核心 (编号, 占用率)
+    busiest: Option<(usize, f32)>,
+}
+
+impl CpuSource {
+    pub fn new(mode: CpuMode) -> Self {
+        let mut system = System::new();
+        system.refresh_cpu();
+
+        let name = match mode {
+            CpuMode::Average => "CPU".to_string(),
+            _ => format!("CPU ({})", mode.label()),
+        };
+
+        CpuSource {
+            system,
+            mode,
+            name,
+            busiest: None,
+        }
+    }
+
+    // 按设置创建
+    pub fn from_settings() -> Self {
+        CpuSource::new(CpuMode::parse(&crate::utils::get_cpu_mode()))
+    }
+}
+
+// 当前系统的逻辑核心数
+pub fn logical_core_count() -> usize {
+    let mut system = System::new();
+    system.refresh_cpu();
+    system.cpus().len()
+}
+
+impl MetricSource for CpuSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn unit(&self) -> &str {
+        "%"
+    }
+
+    fn range(&self) -> (f32, f32) {
+        (0.0, 100.0)
+    }
+
+    fn sample(&mut self) -> Result<f32, String> {
+        self.system.refresh_cpu();
+
+        let usages: Vec<f32> = self.system.cpus().iter().map(|c| c.cpu_usage()).collect();
+        if usages.is_empty() {
+            return Err("无法读取 CPU 信息".to_string());
+        }
+
+        self.busiest = usages
+            .iter()
+            .copied()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+
+        let cpu = match self.mode {
+            CpuMode::Average => usages.iter().sum::<f32>() / (usages.len() as f32),
+            CpuMode::Max => self.busiest.map(|(_, usage)| usage).unwrap_or(0.0),
+            CpuMode::TopMean(n) => {
+                let mut sorted = usages.clone();
+                sorted.sort_by(|a, b| b.total_cmp(a));
+                let n = n.min(sorted.len());
+                sorted[..n].iter().sum::<f32>() / (n as f32)
+            }
+            CpuMode::Core(i) => match usages.get(i) {
+                Some(usage) => *usage,
+                None => return Err(format!("核心 {} 不存在（共 {} 个）", i, usages.len())),
+            },
+        };
+
+        Ok(cpu.clamp(0.0, 100.0))
+    }
+
+    fn detail(&self) -> Option<String> {
+        self.busiest
+            .map(|(id, usage)| format!("最繁忙: 核心 {} ({:.0}%)", id, usage))
+    }
+}
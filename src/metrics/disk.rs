@@ -0,0 +1,244 @@
+// 磁盘指标源：按设备统计读写吞吐量和繁忙时间，换算成 0-100 的繁忙百分比
+//
+// Linux 读取 /proc/diskstats，Windows 通过 IOCTL_DISK_PERFORMANCE 查询物理磁盘计数器
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+use super::MetricSource;
+
+const MB: f64 = 1024.0 * 1024.0;
+
+// 某一时刻单个磁盘的累计计数器
+struct DiskCounters {
+    name: String,
+    read_bytes: u64,
+    write_bytes: u64,
+    // 累计繁忙时间（毫秒）
+    busy_ms: u64,
+}
+
+pub struct DiskSource {
+    // 为空表示任意磁盘（取最繁忙的磁盘）
+    device: String,
+    name: String,
+    last: HashMap<String, DiskCounters>,
+    last_sample: Instant,
+    read_per_sec: f64,
+    write_per_sec: f64,
+    busiest: String,
+}
+
+impl DiskSource {
+    pub fn new(device: String) -> Self {
+        let name = if device.is_empty() {
+            "磁盘".to_string()
+        } else {
+            format!("磁盘 ({})", device)
+        };
+
+        let last = read_disk_counters()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|c| (c.name.clone(), c))
+            .collect();
+
+        DiskSource {
+            device,
+            name,
+            last,
+            last_sample: Instant::now(),
+            read_per_sec: 0.0,
+            write_per_sec: 0.0,
+            busiest: String::new(),
+        }
+    }
+
+    // 按设置创建
+    pub fn from_settings() -> Self {
+        DiskSource::new(crate::utils::get_disk_device())
+    }
+}
+
+// 列出当前系统的磁盘设备名称
+pub fn list_disks() -> Vec<String> {
+    let mut names: Vec<String> = read_disk_counters()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|c| c.name)
+        .collect();
+    names.sort();
+    names
+}
+
+impl MetricSource for DiskSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn unit(&self) -> &str {
+        "%"
+    }
+
+    fn range(&self) -> (f32, f32) {
+        (0.0, 100.0)
+    }
+
+    fn sample(&mut self) -> Result<f32, String> {
+        let counters = read_disk_counters()?;
+        let elapsed_ms = (self.last_sample.elapsed().as_secs_f64() * 1000.0).max(1.0);
+        self.last_sample = Instant::now();
+
+        let mut found = false;
+        let mut busy_max = 0.0f64;
+        let mut read_bytes = 0u64;
+        let mut write_bytes = 0u64;
+        self.busiest.clear();
+
+        for c in &counters {
+            if !self.device.is_empty() && c.name != self.device {
+                continue;
+            }
+            found = true;
+
+            let prev = match self.last.get(&c.name) {
+                Some(prev) => prev,
+                None => continue,
+            };
+            read_bytes += c.read_bytes.saturating_sub(prev.read_bytes);
+            write_bytes += c.write_bytes.saturating_sub(prev.write_bytes);
+
+            let busy = c.busy_ms.saturating_sub(prev.busy_ms) as f64 / elapsed_ms * 100.0;
+            if busy >= busy_max {
+                busy_max = busy;
+                self.busiest = c.name.clone();
+            }
+        }
+
+        self.last = counters.into_iter().map(|c| (c.name.clone(), c)).collect();
+
+        if !found {
+            return Err(format!("找不到磁盘 {}", self.device));
+        }
+
+        self.read_per_sec = read_bytes as f64 / elapsed_ms * 1000.0;
+        self.write_per_sec = write_bytes as f64 / elapsed_ms * 1000.0;
+
+        Ok(busy_max.min(100.0) as f32)
+    }
+
+    fn detail(&self) -> Option<String> {
+        let mut detail = format!(
+            "读取 {:.2} MB/s，写入 {:.2} MB/s",
+            self.read_per_sec / MB,
+            self.write_per_sec / MB,
+        );
+        if self.device.is_empty() && !self.busiest.is_empty() {
+            detail.push_str(&format!("\n最繁忙: {}", self.busiest));
+        }
+        Some(detail)
+    }
+}
+
+// 读取 /proc/diskstats，只保留 /sys/block 下的整盘设备（排除分区、loop、ram 和 zram 设备）
+#[cfg(target_os = "linux")]
+fn read_disk_counters() -> Result<Vec<DiskCounters>, String> {
+    let content = std::fs::read_to_string("/proc/diskstats")
+        .map_err(|e| format!("无法读取 /proc/diskstats: {}", e))?;
+
+    let mut disks = Vec::new();
+    for line in content.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 13 {
+            continue;
+        }
+
+        let name = fields[2];
+        if name.starts_with("loop") || name.starts_with("ram") || name.starts_with("zram") {
+            continue;
+        }
+        if !std::path::Path::new("/sys/block").join(name).exists() {
+            continue;
+        }
+
+        let field = |i: usize| fields[i].parse::<u64>().unwrap_or(0);
+        disks.push(DiskCounters {
+            name: name.to_string(),
+            // 扇区数固定按 512 字节计算
+            read_bytes: field(5) * 512,
+            write_bytes: field(9) * 512,
+            busy_ms: field(12),
+        });
+    }
+
+    Ok(disks)
+}
+
+// 依次打开 \\.\PhysicalDriveN 并查询磁盘性能计数器
+#[cfg(windows)]
+fn read_disk_counters() -> Result<Vec<DiskCounters>, String> {
+    use std::ptr::null_mut;
+    use winapi::shared::minwindef::{DWORD, LPVOID};
+    use winapi::um::fileapi::{CreateFileW, OPEN_EXISTING};
+    use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+    use winapi::um::ioapiset::DeviceIoControl;
+    use winapi::um::winioctl::{DISK_PERFORMANCE, IOCTL_DISK_PERFORMANCE};
+    use winapi::um::winnt::{FILE_SHARE_READ, FILE_SHARE_WRITE};
+
+    let mut disks = Vec::new();
+    for index in 0..16 {
+        let path = crate::utils::to_wide_null(format!("\\\\.\\PhysicalDrive{}", index));
+        let handle = unsafe {
+            CreateFileW(
+                path.as_ptr(),
+                0,
+                FILE_SHARE_READ | FILE_SHARE_WRITE,
+                null_mut(),
+                OPEN_EXISTING,
+                0,
+                null_mut(),
+            )
+        };
+        if handle == INVALID_HANDLE_VALUE {
+            continue;
+        }
+
+        let mut perf: DISK_PERFORMANCE = unsafe { std::mem::zeroed() };
+        let mut returned: DWORD = 0;
+        let ok = unsafe {
+            DeviceIoControl(
+                handle,
+                IOCTL_DISK_PERFORMANCE,
+                null_mut(),
+                0,
+                &mut perf as *mut _ as LPVOID,
+                std::mem::size_of::<DISK_PERFORMANCE>() as DWORD,
+                &mut returned,
+                null_mut(),
+            )
+        };
+        unsafe { CloseHandle(handle) };
+        if ok == 0 {
+            continue;
+        }
+
+        // 时间计数器单位为 100 纳秒
+        let (query_time, idle_time) = unsafe { (*perf.QueryTime.QuadPart(), *perf.IdleTime.QuadPart()) };
+        disks.push(DiskCounters {
+            name: format!("PhysicalDrive{}", index),
+            read_bytes: unsafe { *perf.BytesRead.QuadPart() } as u64,
+            write_bytes: unsafe { *perf.BytesWritten.QuadPart() } as u64,
+            busy_ms: (query_time - idle_time).max(0) as u64 / 10_000,
+        });
+    }
+
+    if disks.is_empty() {
+        return Err("无法查询磁盘性能计数器".to_string());
+    }
+    Ok(disks)
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn read_disk_counters() -> Result<Vec<DiskCounters>, String> {
+    Err("当前系统不支持磁盘指标".to_string())
+}
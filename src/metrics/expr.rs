@@ -0,0 +1,528 @@
+// 派生指标表达式：解析并计算由其他指标组合而成的数值
+//
+// 语法：
+//   数字和指标名（如 cpu、mem，取值为换算后的 0-100）
+//   运算符 + - * /，比较 < <= > >= == !=（成立为 1，否则为 0），逻辑 && ||，括号
+//   函数 max(a, b, ...)、min(a, b, ...)、avg(a, b, ...)、abs(x)、clamp(x, 下限, 上限)、if(条件, 真值, 假值)
+// 例如：max(cpu, mem)、0.7*cpu + 0.3*io、if(battery < 20, 100, cpu)
+// 暂时无法采样的指标取值为 NaN：比较和条件视为不成立，max、min 忽略它，其余运算的结果为 NaN
+
+use std::fmt;
+
+// 解析错误，pos 为出错位置（从 0 开始的字符偏移）
+pub struct ParseError {
+    pub pos: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    fn new(pos: usize, message: impl Into<String>) -> Self {
+        ParseError { pos, message: message.into() }
+    }
+
+    // 输出表达式原文，并在下一行用 ^ 标出出错位置
+    pub fn display(&self, source: &str) -> String {
+        format!("{}\n{}\n{}^", self, source, " ".repeat(self.pos))
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "第 {} 列: {}", self.pos + 1, self.message)
+    }
+}
+
+#[derive(Clone, PartialEq)]
+enum Token {
+    Number(f32),
+    Ident(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Comma,
+    End,
+}
+
+// 拆分词法单元，返回 (单元, 起始位置)
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, ParseError> {
+    const OPS: &[&str] = &["<=", ">=", "==", "!=", "&&", "||", "<", ">", "+", "-", "*", "/"];
+
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let literal: String = chars[start..i].iter().collect();
+            let value = literal
+                .parse::<f32>()
+                .map_err(|_| ParseError::new(start, format!("无效的数字: {}", literal)))?;
+            tokens.push((Token::Number(value), start));
+        } else if c.is_alphabetic() || c == '_' {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push((Token::Ident(chars[start..i].iter().collect()), start));
+        } else if c == '(' {
+            tokens.push((Token::LParen, start));
+            i += 1;
+        } else if c == ')' {
+            tokens.push((Token::RParen, start));
+            i += 1;
+        } else if c == ',' {
+            tokens.push((Token::Comma, start));
+            i += 1;
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let op = OPS
+                .iter()
+                .find(|op| rest.starts_with(*op))
+                .ok_or_else(|| ParseError::new(start, format!("无法识别的字符: {}", c)))?;
+            tokens.push((Token::Op(op), start));
+            i += op.len();
+        }
+    }
+
+    tokens.push((Token::End, chars.len()));
+    Ok(tokens)
+}
+
+#[derive(Clone, Copy)]
+enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    And,
+    Or,
+}
+
+#[derive(Clone, Copy)]
+enum Func {
+    Max,
+    Min,
+    Avg,
+    Abs,
+    Clamp,
+    If,
+}
+
+impl Func {
+    fn lookup(name: &str) -> Option<Func> {
+        match name {
+            "max" => Some(Func::Max),
+            "min" => Some(Func::Min),
+            "avg" => Some(Func::Avg),
+            "abs" => Some(Func::Abs),
+            "clamp" => Some(Func::Clamp),
+            "if" => Some(Func::If),
+            _ => None,
+        }
+    }
+
+    // 参数个数是否合法，不合法时返回说明
+    fn check_arity(self, count: usize) -> Result<(), &'static str> {
+        match self {
+            Func::Max | Func::Min | Func::Avg if count == 0 => Err("至少需要 1 个参数"),
+            Func::Abs if count != 1 => Err("需要 1 个参数"),
+            Func::Clamp | Func::If if count != 3 => Err("需要 3 个参数"),
+            _ => Ok(()),
+        }
+    }
+}
+
+enum Node {
+    Number(f32),
+    // 指标值，下标对应 Expr::variables
+    Var(usize),
+    Neg(Box<Node>),
+    Binary(BinOp, Box<Node>, Box<Node>),
+    Call(Func, Vec<Node>),
+}
+
+// 解析后的表达式
+pub struct Expr {
+    root: Node,
+    variables: Vec<String>,
+}
+
+impl Expr {
+    // 解析表达式；resolve 把表达式中的名称映射为指标 id，无法识别时返回 None
+    pub fn parse(text: &str, resolve: &dyn Fn(&str) -> Option<String>) -> Result<Expr, ParseError> {
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            index: 0,
+            resolve,
+            variables: Vec::new(),
+        };
+
+        if parser.peek() == &Token::End {
+            return Err(ParseError::new(0, "表达式为空"));
+        }
+        let root = parser.parse_or()?;
+        if parser.peek() != &Token::End {
+            return Err(ParseError::new(parser.pos(), "多余的内容"));
+        }
+
+        Ok(Expr { root, variables: parser.variables })
+    }
+
+    // 表达式引用的指标 id，按首次出现的顺序排列，不重复
+    pub fn variables(&self) -> &[String] {
+        &self.variables
+    }
+
+    // 按 variables 的顺序代入指标值并计算
+    pub fn eval(&self, values: &[f32]) -> f32 {
+        eval(&self.root, values)
+    }
+}
+
+struct Parser<'a> {
+    tokens: Vec<(Token, usize)>,
+    index: usize,
+    resolve: &'a dyn Fn(&str) -> Option<String>,
+    variables: Vec<String>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.index].0
+    }
+
+    fn pos(&self) -> usize {
+        self.tokens[self.index].1
+    }
+
+    fn next(&mut self) -> (Token, usize) {
+        let token = self.tokens[self.index].clone();
+        if token.0 != Token::End {
+            self.index += 1;
+        }
+        token
+    }
+
+    // 当前单元是给定运算符之一时取出并返回对应的运算
+    fn take_op(&mut self, ops: &[(&str, BinOp)]) -> Option<BinOp> {
+        let op = match self.peek() {
+            Token::Op(op) => ops.iter().find(|(s, _)| s == op).map(|(_, op)| *op)?,
+            _ => return None,
+        };
+        self.index += 1;
+        Some(op)
+    }
+
+    // 解析左结合的一层二元运算
+    fn parse_binary(
+        &mut self,
+        ops: &[(&str, BinOp)],
+        operand: fn(&mut Self) -> Result<Node, ParseError>,
+    ) -> Result<Node, ParseError> {
+        let mut left = operand(self)?;
+        while let Some(op) = self.take_op(ops) {
+            let right = operand(self)?;
+            left = Node::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_or(&mut self) -> Result<Node, ParseError> {
+        self.parse_binary(&[("||", BinOp::Or)], Self::parse_and)
+    }
+
+    fn parse_and(&mut self) -> Result<Node, ParseError> {
+        self.parse_binary(&[("&&", BinOp::And)], Self::parse_comparison)
+    }
+
+    // 比较运算不能连写，a < b < c 视为错误
+    fn parse_comparison(&mut self) -> Result<Node, ParseError> {
+        const OPS: &[(&str, BinOp)] = &[
+            ("<", BinOp::Lt),
+            ("<=", BinOp::Le),
+            (">", BinOp::Gt),
+            (">=", BinOp::Ge),
+            ("==", BinOp::Eq),
+            ("!=", BinOp::Ne),
+        ];
+        let left = self.parse_additive()?;
+        let op = match self.take_op(OPS) {
+            Some(op) => op,
+            None => return Ok(left),
+        };
+        let right = self.parse_additive()?;
+        if self.take_op(OPS).is_some() {
+            return Err(ParseError::new(self.tokens[self.index - 1].1, "比较运算不能连用，请使用 &&"));
+        }
+        Ok(Node::Binary(op, Box::new(left), Box::new(right)))
+    }
+
+    fn parse_additive(&mut self) -> Result<Node, ParseError> {
+        self.parse_binary(&[("+", BinOp::Add), ("-", BinOp::Sub)], Self::parse_term)
+    }
+
+    fn parse_term(&mut self) -> Result<Node, ParseError> {
+        self.parse_binary(&[("*", BinOp::Mul), ("/", BinOp::Div)], Self::parse_unary)
+    }
+
+    fn parse_unary(&mut self) -> Result<Node, ParseError> {
+        if self.peek() == &Token::Op("-") {
+            self.index += 1;
+            return Ok(Node::Neg(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Node, ParseError> {
+        let (token, pos) = self.next();
+        match token {
+            Token::Number(value) => Ok(Node::Number(value)),
+            Token::LParen => {
+                let inner = self.parse_or()?;
+                self.expect_close(pos)?;
+                Ok(inner)
+            }
+            Token::Ident(name) if self.peek() == &Token::LParen => self.parse_call(&name, pos),
+            Token::Ident(name) => {
+                let id = (self.resolve)(&name)
+                    .ok_or_else(|| ParseError::new(pos, format!("未知的指标: {}", name)))?;
+                let index = match self.variables.iter().position(|v| *v == id) {
+                    Some(index) => index,
+                    None => {
+                        self.variables.push(id);
+                        self.variables.len() - 1
+                    }
+                };
+                Ok(Node::Var(index))
+            }
+            Token::End => Err(ParseError::new(pos, "表达式不完整")),
+            _ => Err(ParseError::new(pos, "此处应为数字、指标名或括号")),
+        }
+    }
+
+    fn parse_call(&mut self, name: &str, pos: usize) -> Result<Node, ParseError> {
+        let func = Func::lookup(name).ok_or_else(|| ParseError::new(pos, format!("未知的函数: {}", name)))?;
+        let (_, open) = self.next();
+
+        let mut args = Vec::new();
+        if self.peek() != &Token::RParen {
+            loop {
+                args.push(self.parse_or()?);
+                if self.peek() != &Token::Comma {
+                    break;
+                }
+                self.index += 1;
+            }
+        }
+        self.expect_close(open)?;
+
+        func.check_arity(args.len())
+            .map_err(|e| ParseError::new(pos, format!("{} {}", name, e)))?;
+        Ok(Node::Call(func, args))
+    }
+
+    // 读取右括号，open 为对应左括号的位置
+    fn expect_close(&mut self, open: usize) -> Result<(), ParseError> {
+        match self.peek() {
+            Token::RParen => {
+                self.index += 1;
+                Ok(())
+            }
+            Token::End => Err(ParseError::new(open, "括号没有闭合")),
+            _ => Err(ParseError::new(self.pos(), "此处应为 )")),
+        }
+    }
+}
+
+// NaN 视为不成立
+fn truth(value: f32) -> bool {
+    value != 0.0 && !value.is_nan()
+}
+
+fn flag(value: bool) -> f32 {
+    if value { 1.0 } else { 0.0 }
+}
+
+fn eval(node: &Node, values: &[f32]) -> f32 {
+    match node {
+        Node::Number(value) => *value,
+        Node::Var(index) => values.get(*index).copied().unwrap_or(0.0),
+        Node::Neg(inner) => -eval(inner, values),
+        Node::Binary(op, left, right) => {
+            let l = eval(left, values);
+            let r = || eval(right, values);
+            match op {
+                // && 和 || 短路求值
+                BinOp::And => flag(truth(l) && truth(r())),
+                BinOp::Or => flag(truth(l) || truth(r())),
+                BinOp::Add => l + r(),
+                BinOp::Sub => l - r(),
+                BinOp::Mul => l * r(),
+                BinOp::Div => l / r(),
+                BinOp::Lt => flag(l < r()),
+                BinOp::Le => flag(l <= r()),
+                BinOp::Gt => flag(l > r()),
+                BinOp::Ge => flag(l >= r()),
+                BinOp::Eq => flag(l == r()),
+                // 与 NaN 比较同样不成立
+                BinOp::Ne => flag(l.partial_cmp(&r()).is_some_and(|order| order.is_ne())),
+            }
+        }
+        Node::Call(func, args) => {
+            let arg = |i: usize| eval(&args[i], values);
+            match func {
+                Func::Max => args.iter().map(|a| eval(a, values)).fold(f32::NEG_INFINITY, f32::max),
+                Func::Min => args.iter().map(|a| eval(a, values)).fold(f32::INFINITY, f32::min),
+                Func::Avg => args.iter().map(|a| eval(a, values)).sum::<f32>() / args.len() as f32,
+                Func::Abs => arg(0).abs(),
+                // f32::max 会忽略 NaN，无效值需原样返回
+                Func::Clamp => {
+                    let x = arg(0);
+                    if x.is_nan() {
+                        x
+                    } else {
+                        x.max(arg(1)).min(arg(2))
+                    }
+                }
+                Func::If => {
+                    if truth(arg(0)) {
+                        arg(1)
+                    } else {
+                        arg(2)
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 只认识 a、b、c 三个指标
+    fn resolve(name: &str) -> Option<String> {
+        ["a", "b", "c"].contains(&name).then(|| name.to_string())
+    }
+
+    fn parse(text: &str) -> Expr {
+        Expr::parse(text, &resolve).unwrap_or_else(|e| panic!("{}", e.display(text)))
+    }
+
+    fn calc(text: &str, values: &[f32]) -> f32 {
+        parse(text).eval(values)
+    }
+
+    fn error(text: &str) -> String {
+        match Expr::parse(text, &resolve) {
+            Ok(_) => panic!("{} 应解析失败", text),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(calc("1 + 2 * 3", &[]), 7.0);
+        assert_eq!(calc("(1 + 2) * 3", &[]), 9.0);
+        assert_eq!(calc("8 - 4 - 2", &[]), 2.0);
+        assert_eq!(calc("8 / 4 / 2", &[]), 1.0);
+        assert_eq!(calc("1 + 1 < 3", &[]), 1.0);
+        assert_eq!(calc("1 < 2 && 3 < 2 || 1", &[]), 1.0);
+        assert_eq!(calc("0 || 1 && 0", &[]), 0.0);
+        assert_eq!(calc("0.7*a + 0.3*b", &[50.0, 100.0]), 65.0);
+    }
+
+    #[test]
+    fn unary_minus() {
+        assert_eq!(calc("-2 * 3", &[]), -6.0);
+        assert_eq!(calc("2 - -3", &[]), 5.0);
+        assert_eq!(calc("--a", &[4.0]), 4.0);
+        assert_eq!(calc("abs(-a)", &[4.0]), 4.0);
+    }
+
+    #[test]
+    fn variables_are_deduplicated_in_order() {
+        let expr = parse("max(b, a, b)");
+        assert_eq!(expr.variables(), ["b", "a"]);
+        assert_eq!(expr.eval(&[10.0, 20.0]), 20.0);
+    }
+
+    #[test]
+    fn logic_short_circuits_unavailable_operand() {
+        assert_eq!(calc("0 && a", &[f32::NAN]), 0.0);
+        assert_eq!(calc("1 || a", &[f32::NAN]), 1.0);
+        assert_eq!(calc("1 && a", &[f32::NAN]), 0.0);
+        assert_eq!(calc("0 || a", &[f32::NAN]), 0.0);
+        assert_eq!(calc("2 && 3", &[]), 1.0);
+    }
+
+    #[test]
+    fn unavailable_values() {
+        assert_eq!(calc("if(a < 20, 100, b)", &[f32::NAN, 40.0]), 40.0);
+        assert_eq!(calc("if(a, 1, 2)", &[f32::NAN]), 2.0);
+        assert_eq!(calc("a != 5", &[f32::NAN]), 0.0);
+        assert_eq!(calc("max(a, b)", &[f32::NAN, 40.0]), 40.0);
+        assert_eq!(calc("min(a, b)", &[f32::NAN, 40.0]), 40.0);
+        assert!(calc("a + b", &[f32::NAN, 40.0]).is_nan());
+        assert!(calc("avg(a, b)", &[f32::NAN, 40.0]).is_nan());
+        assert!(calc("clamp(a, 0, 100)", &[f32::NAN]).is_nan());
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(calc("max(1, 5, 3)", &[]), 5.0);
+        assert_eq!(calc("min(4)", &[]), 4.0);
+        assert_eq!(calc("avg(1, 2, 3, 6)", &[]), 3.0);
+        assert_eq!(calc("clamp(150, 0, 100)", &[]), 100.0);
+        assert_eq!(calc("clamp(-5, 0, 100)", &[]), 0.0);
+        assert_eq!(calc("if(1, 2, 3)", &[]), 2.0);
+        assert_eq!(calc("if(0, 2, 3)", &[]), 3.0);
+    }
+
+    #[test]
+    fn function_arity() {
+        assert_eq!(error("if(1, 2)"), "第 1 列: if 需要 3 个参数");
+        assert_eq!(error("1 + clamp(a, 0)"), "第 5 列: clamp 需要 3 个参数");
+        assert_eq!(error("clamp(a, 0, 1, 2)"), "第 1 列: clamp 需要 3 个参数");
+        assert_eq!(error("abs()"), "第 1 列: abs 需要 1 个参数");
+        assert_eq!(error("max()"), "第 1 列: max 至少需要 1 个参数");
+        assert_eq!(error("sqrt(4)"), "第 1 列: 未知的函数: sqrt");
+    }
+
+    #[test]
+    fn error_columns() {
+        assert_eq!(error("a + foo"), "第 5 列: 未知的指标: foo");
+        assert_eq!(error(""), "第 1 列: 表达式为空");
+        assert_eq!(error("a +"), "第 4 列: 表达式不完整");
+        assert_eq!(error("max(a, b"), "第 4 列: 括号没有闭合");
+        assert_eq!(error("a b"), "第 3 列: 多余的内容");
+        assert_eq!(error("a < b < c"), "第 7 列: 比较运算不能连用，请使用 &&");
+        assert_eq!(error("a # b"), "第 3 列: 无法识别的字符: #");
+        assert_eq!(error("1.2.3"), "第 1 列: 无效的数字: 1.2.3");
+    }
+
+    #[test]
+    fn error_marker() {
+        let e = Expr::parse("a + foo", &resolve).err().unwrap();
+        assert_eq!(e.display("a + foo"), "第 5 列: 未知的指标: foo\na + foo\n    ^");
+    }
+
+    #[test]
+    fn division_by_zero() {
+        assert_eq!(calc("a / 0", &[5.0]), f32::INFINITY);
+        assert!(calc("0 / 0", &[]).is_nan());
+    }
+}
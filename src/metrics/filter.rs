@@ -0,0 +1,238 @@
+// 平滑滤波模块：在采样值进入速度换算之前过滤抖动
+//
+// 滤波器在设置中以字符串描述，多个滤波器用逗号串联，按顺序依次处理：
+//   none          不滤波
+//   ema:0.3       指数移动平均，每次采样的权重 alpha 为 0.3
+//   ema:2s        指数移动平均，半衰期 2 秒（与采样间隔无关）
+//   mean:5        最近 5 次采样的滑动平均
+//   median:5      最近 5 次采样的滑动中位数
+//   deadband:5    迟滞死区，与当前输出相差不足 5 时保持输出不变
+
+use std::collections::VecDeque;
+use std::time::Instant;
+
+// 滑动窗口的最大采样次数
+const MAX_WINDOW: usize = 1000;
+
+enum Stage {
+    Ema { alpha: f32, value: Option<f32> },
+    EmaHalfLife { half_life: f32, value: Option<f32>, last: Instant },
+    Mean { window: VecDeque<f32>, size: usize },
+    Median { window: VecDeque<f32>, size: usize },
+    Deadband { width: f32, value: Option<f32> },
+}
+
+impl Stage {
+    fn parse(spec: &str) -> Result<Stage, String> {
+        let (kind, arg) = spec.split_once(':').unwrap_or((spec, ""));
+        let arg = arg.trim();
+        let number = |s: &str| {
+            s.parse::<f32>()
+                .ok()
+                .filter(|v| v.is_finite() && *v > 0.0)
+                .ok_or_else(|| format!("滤波参数无效: {}", spec))
+        };
+        let size = |s: &str| {
+            s.parse::<usize>()
+                .ok()
+                .filter(|n| *n > 0 && *n <= MAX_WINDOW)
+                .ok_or_else(|| format!("滤波窗口无效: {}", spec))
+        };
+
+        match kind.trim() {
+            "ema" => match arg.strip_suffix('s') {
+                Some(secs) => Ok(Stage::EmaHalfLife {
+                    half_life: number(secs)?,
+                    value: None,
+                    last: Instant::now(),
+                }),
+                None => Ok(Stage::Ema {
+                    alpha: number(arg)?.min(1.0),
+                    value: None,
+                }),
+            },
+            "mean" => {
+                let size = size(arg)?;
+                Ok(Stage::Mean { window: VecDeque::with_capacity(size), size })
+            }
+            "median" => {
+                let size = size(arg)?;
+                Ok(Stage::Median { window: VecDeque::with_capacity(size), size })
+            }
+            "deadband" => Ok(Stage::Deadband { width: number(arg)?, value: None }),
+            _ => Err(format!("未知的滤波器: {}", spec)),
+        }
+    }
+
+    fn apply(&mut self, input: f32) -> f32 {
+        match self {
+            Stage::Ema { alpha, value } => {
+                let next = match value {
+                    Some(prev) => *prev + *alpha * (input - *prev),
+                    None => input,
+                };
+                *value = Some(next);
+                next
+            }
+            Stage::EmaHalfLife { half_life, value, last } => {
+                let dt = last.elapsed().as_secs_f32();
+                *last = Instant::now();
+                let alpha = 1.0 - 0.5f32.powf(dt / *half_life);
+                let next = match value {
+                    Some(prev) => *prev + alpha * (input - *prev),
+                    None => input,
+                };
+                *value = Some(next);
+                next
+            }
+            Stage::Mean { window, size } => {
+                push_window(window, *size, input);
+                window.iter().sum::<f32>() / window.len() as f32
+            }
+            Stage::Median { window, size } => {
+                push_window(window, *size, input);
+                let mut sorted: Vec<f32> = window.iter().copied().collect();
+                sorted.sort_by(|a, b| a.total_cmp(b));
+                let mid = sorted.len() / 2;
+                if sorted.len().is_multiple_of(2) {
+                    (sorted[mid - 1] + sorted[mid]) / 2.0
+                } else {
+                    sorted[mid]
+                }
+            }
+            Stage::Deadband { width, value } => match value {
+                Some(prev) if (input - *prev).abs() < *width => *prev,
+                _ => {
+                    *value = Some(input);
+                    input
+                }
+            },
+        }
+    }
+}
+
+fn push_window(window: &mut VecDeque<f32>, size: usize, input: f32) {
+    if window.len() == size {
+        window.pop_front();
+    }
+    window.push_back(input);
+}
+
+// 串联的滤波器
+pub struct Filter {
+    stages: Vec<Stage>,
+}
+
+impl Filter {
+    pub fn parse(spec: &str) -> Result<Filter, String> {
+        let spec = spec.trim();
+        if spec.is_empty() || spec == "none" {
+            return Ok(Filter { stages: Vec::new() });
+        }
+        let stages = spec
+            .split(',')
+            .map(|stage| Stage::parse(stage.trim()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Filter { stages })
+    }
+
+    // 按设置为指定指标创建滤波器，设置无效时不滤波并输出错误
+    pub fn from_settings(metric_id: &str) -> Filter {
+        Filter::parse(&crate::utils::get_metric_filter(metric_id)).unwrap_or_else(|e| {
+            eprintln!("{}: {}", metric_id, e);
+            Filter { stages: Vec::new() }
+        })
+    }
+
+    pub fn apply(&mut self, input: f32) -> f32 {
+        self.stages.iter_mut().fold(input, |value, stage| stage.apply(value))
+    }
+}
+
+// 菜单中提供的预设滤波器：(显示名称, 设置值)
+pub const FILTER_PRESETS: &[(&str, &str)] = &[
+    ("不平滑", "none"),
+    ("指数平均 (α=0.3)", "ema:0.3"),
+    ("指数平均 (半衰期 2 秒)", "ema:2s"),
+    ("滑动平均 (5 次)", "mean:5"),
+    ("滑动中位数 (5 次)", "median:5"),
+    ("死区 5", "deadband:5"),
+    ("中位数 + 指数平均", "median:5,ema:0.3"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn run(spec: &str, inputs: &[f32]) -> Vec<f32> {
+        let mut filter = Filter::parse(spec).unwrap();
+        inputs.iter().map(|&input| filter.apply(input)).collect()
+    }
+
+    #[test]
+    fn no_filter() {
+        assert_eq!(run("none", &[1.0, 50.0, 3.0]), [1.0, 50.0, 3.0]);
+        assert_eq!(run(" ", &[7.0]), [7.0]);
+    }
+
+    #[test]
+    fn ema_alpha() {
+        assert_eq!(run("ema:0.5", &[0.0, 100.0, 100.0, 0.0]), [0.0, 50.0, 75.0, 37.5]);
+        // alpha 大于 1 时按 1 处理，即不平滑
+        assert_eq!(run("ema:3", &[10.0, 90.0]), [10.0, 90.0]);
+    }
+
+    #[test]
+    fn ema_half_life() {
+        let mut filter = Filter::parse("ema:2s").unwrap();
+        assert_eq!(filter.apply(0.0), 0.0);
+        // 经过一个半衰期后与新值的差距减半
+        if let Stage::EmaHalfLife { last, .. } = &mut filter.stages[0] {
+            *last = Instant::now() - Duration::from_secs(2);
+        }
+        assert!((filter.apply(100.0) - 50.0).abs() < 0.5);
+        // 几乎没有经过时间时保持原值
+        assert!((filter.apply(0.0) - 50.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn sliding_mean() {
+        assert_eq!(run("mean:3", &[3.0, 6.0, 9.0, 30.0, 0.0]), [3.0, 4.5, 6.0, 15.0, 13.0]);
+    }
+
+    #[test]
+    fn sliding_median() {
+        // 奇数窗口取中间值，窗口未满或偶数窗口取中间两个值的平均
+        assert_eq!(run("median:3", &[5.0, 1.0, 100.0, 2.0, 3.0]), [5.0, 3.0, 5.0, 2.0, 3.0]);
+        assert_eq!(run("median:4", &[4.0, 1.0, 100.0, 2.0, 3.0]), [4.0, 2.5, 4.0, 3.0, 2.5]);
+    }
+
+    #[test]
+    fn deadband_hysteresis() {
+        assert_eq!(
+            run("deadband:5", &[50.0, 53.0, 54.9, 55.0, 51.0, 50.1, 40.0]),
+            [50.0, 50.0, 50.0, 55.0, 55.0, 55.0, 40.0]
+        );
+    }
+
+    #[test]
+    fn chained_stages() {
+        // 中位数先去掉尖峰，再做指数平均
+        assert_eq!(run("median:3, ema:0.5", &[10.0, 10.0, 90.0, 10.0]), [10.0, 10.0, 10.0, 10.0]);
+        assert_eq!(run("mean:2,deadband:10", &[0.0, 10.0, 20.0, 40.0]), [0.0, 0.0, 15.0, 30.0]);
+    }
+
+    #[test]
+    fn rejects_invalid_stages() {
+        for spec in [
+            "ema:0", "ema:-1", "ema:0s", "ema:x", "ema:nan", "mean:0", "mean:-1", "mean:1.5", "median:",
+            "deadband:-1", "deadband:0", "unknown:3", "mean:3,,ema:0.3",
+        ] {
+            assert!(Filter::parse(spec).is_err(), "{}", spec);
+        }
+        assert!(Filter::parse("mean:1000").is_ok());
+        assert_eq!(Filter::parse("mean:1001").err(), Some("滤波窗口无效: mean:1001".to_string()));
+        assert!(Filter::parse("median:100000000000").is_err());
+    }
+}
@@ -0,0 +1,98 @@
+// 负载均值指标源：1/5/15 分钟负载均值除以逻辑核心数，以百分比表示
+//
+// 负载超过核心数（百分比超过 100%）说明有任务在排队，上限可通过设置调整；
+// Linux 下同时从 /proc/stat 读取正在运行和阻塞的进程数显示在提示中
+
+use sysinfo::{System, SystemExt};
+
+use super::MetricSource;
+
+pub struct LoadSource {
+    system: System,
+    // 负载均值窗口：1、5 或 15 分钟
+    window: u32,
+    name: String,
+    ceiling: f32,
+    cores: usize,
+    // 最近一次采样的 (1, 5, 15 分钟负载)
+    last: Option<(f64, f64, f64)>,
+}
+
+impl LoadSource {
+    pub fn new(window: u32, ceiling_percent: u32) -> Self {
+        let window = match window {
+            5 | 15 => window,
+            _ => 1,
+        };
+        let mut system = System::new();
+        system.refresh_cpu();
+
+        LoadSource {
+            cores: system.cpus().len().max(1),
+            system,
+            window,
+            name: format!("负载 ({} 分钟)", window),
+            ceiling: ceiling_percent.max(1) as f32,
+            last: None,
+        }
+    }
+
+    // 按设置创建
+    pub fn from_settings() -> Self {
+        LoadSource::new(
+            crate::utils::get_load_window(),
+            crate::utils::get_load_ceiling_percent(),
+        )
+    }
+}
+
+impl MetricSource for LoadSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn unit(&self) -> &str {
+        "%"
+    }
+
+    fn range(&self) -> (f32, f32) {
+        (0.0, self.ceiling)
+    }
+
+    fn sample(&mut self) -> Result<f32, String> {
+        if cfg!(windows) {
+            return Err("Windows 不提供负载均值".to_string());
+        }
+
+        let load = self.system.load_average();
+        self.last = Some((load.one, load.five, load.fifteen));
+
+        let value = match self.window {
+            5 => load.five,
+            15 => load.fifteen,
+            _ => load.one,
+        };
+        Ok((value / self.cores as f64 * 100.0) as f32)
+    }
+
+    fn detail(&self) -> Option<String> {
+        let (one, five, fifteen) = self.last?;
+        let mut detail = format!("{:.2} {:.2} {:.2}（{} 核）", one, five, fifteen, self.cores);
+        if let Some((running, blocked)) = read_run_queue() {
+            detail.push_str(&format!("\n运行 {}，阻塞 {}", running, blocked));
+        }
+        Some(detail)
+    }
+}
+
+// 从 /proc/stat 读取 (procs_running, procs_blocked)
+fn read_run_queue() -> Option<(u64, u64)> {
+    let content = std::fs::read_to_string("/proc/stat").ok()?;
+    let field = |name: &str| {
+        content
+            .lines()
+            .find_map(|line| line.strip_prefix(name))
+            .and_then(|v| v.trim().parse::<u64>().ok())
+    };
+    Some((field("procs_running ")?, field("procs_blocked ")?))
+}
@@ -0,0 +1,69 @@
+// 内存指标源：物理内存和交换区的占用率
+
+use sysinfo::{System, SystemExt};
+
+use super::MetricSource;
+
+const GIB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+// 采样对象：物理内存或交换区
+#[derive(Clone, Copy, PartialEq)]
+pub enum MemoryKind {
+    Ram,
+    Swap,
+}
+
+pub struct MemorySource {
+    system: System,
+    kind: MemoryKind,
+}
+
+impl MemorySource {
+    pub fn new(kind: MemoryKind) -> Self {
+        MemorySource {
+            system: System::new(),
+            kind,
+        }
+    }
+}
+
+impl MetricSource for MemorySource {
+    fn name(&self) -> &str {
+        match self.kind {
+            MemoryKind::Ram => "内存",
+            MemoryKind::Swap => "交换区",
+        }
+    }
+
+    fn unit(&self) -> &str {
+        "%"
+    }
+
+    fn range(&self) -> (f32, f32) {
+        (0.0, 100.0)
+    }
+
+    fn sample(&mut self) -> Result<f32, String> {
+        self.system.refresh_memory();
+
+        let (used, total) = match self.kind {
+            MemoryKind::Ram => (self.system.used_memory(), self.system.total_memory()),
+            MemoryKind::Swap => (self.system.used_swap(), self.system.total_swap()),
+        };
+        if total == 0 {
+            return Err("未启用".to_string());
+        }
+
+        Ok((used as f64 / total as f64 * 100.0) as f32)
+    }
+
+    fn detail(&self) -> Option<String> {
+        Some(format!(
+            "内存 {:.1}/{:.1} GB，交换区 {:.1}/{:.1} GB",
+            self.system.used_memory() as f64 / GIB,
+            self.system.total_memory() as f64 / GIB,
+            self.system.used_swap() as f64 / GIB,
+            self.system.total_swap() as f64 / GIB,
+        ))
+    }
+}
@@ -0,0 +1,305 @@
+// 指标源模块：定义统一的指标采样接口，以及内置指标源的注册表
+
+mod battery;
+mod cgroup;
+mod command;
+mod composite;
+mod cpu;
+mod disk;
+mod expr;
+mod filter;
+mod load;
+mod memory;
+mod network;
+mod process;
+mod prometheus;
+mod psi;
+mod push;
+mod thermal;
+mod trace;
+
+pub use battery::{BatteryKind, BatterySource};
+pub use cgroup::{CgroupResource, CgroupSource};
+pub use command::CommandSource;
+pub use composite::CompositeSource;
+pub use cpu::{CpuMode, CpuSource};
+pub use disk::DiskSource;
+pub use filter::Filter;
+pub use load::LoadSource;
+pub use memory::{MemoryKind, MemorySource};
+pub use network::NetworkSource;
+pub use process::{ProcessSource, ProcessTarget};
+pub use prometheus::PrometheusSource;
+pub use psi::{PsiResource, PsiSource};
+pub use push::{default_push_address, start_push_listener, PushSource};
+pub use thermal::ThermalSource;
+pub use trace::{ReplaySource, TraceRecorder};
+
+pub const METRIC_CPU: &str = "cpu";
+pub const METRIC_MEMORY: &str = "memory";
+pub const METRIC_SWAP: &str = "swap";
+pub const METRIC_NETWORK: &str = "network";
+pub const METRIC_DISK: &str = "disk";
+pub const METRIC_PSI_CPU: &str = "psi_cpu";
+pub const METRIC_PSI_MEMORY: &str = "psi_memory";
+pub const METRIC_PSI_IO: &str = "psi_io";
+pub const METRIC_CGROUP_CPU: &str = "cgroup_cpu";
+pub const METRIC_CGROUP_MEMORY: &str = "cgroup_memory";
+pub const METRIC_PROCESS: &str = "process";
+pub const METRIC_THERMAL: &str = "thermal";
+pub const METRIC_BATTERY: &str = "battery";
+pub const METRIC_BATTERY_DRAIN: &str = "battery_drain";
+pub const METRIC_LOAD: &str = "load";
+pub const METRIC_COMMAND: &str = "command";
+pub const METRIC_PROMETHEUS: &str = "prometheus";
+pub const METRIC_REPLAY: &str = "replay";
+pub const METRIC_COMPOSITE: &str = "composite";
+
+// 指标源附带的状态，动画层据此调整显示
+#[derive(Clone, Copy, PartialEq)]
+pub enum MetricState {
+    Normal,
+    // 正在充电，动画倒放
+    Charging,
+}
+
+// 指标源接口：每个指标源采样一个数值，并声明名称、单位和取值范围
+pub trait MetricSource: Send {
+    // 显示名称，用于托盘提示和菜单
+    fn name(&self) -> &str;
+
+    // 数值单位
+    fn unit(&self) -> &str;
+
+    // 取值范围 (最小值, 最大值)，用于换算成 0-100 的动画速度
+    fn range(&self) -> (f32, f32);
+
+    // 采样一次，返回原始数值
+    fn sample(&mut self) -> Result<f32, String>;
+
+    // 附加说明，显示在托盘提示的第二行
+    fn detail(&self) -> Option<String> {
+        None
+    }
+
+    // 当前状态
+    fn state(&self) -> MetricState {
+        MetricState::Normal
+    }
+}
+
+// 将原始数值按取值范围换算成 0-100 的百分比
+pub fn normalize(value: f32, range: (f32, f32)) -> f32 {
+    let (min, max) = range;
+    if max <= min {
+        return 0.0;
+    }
+    ((value - min) / (max - min) * 100.0).clamp(0.0, 100.0)
+}
+
+// 指标源构造函数
+type SourceFactory = fn() -> Box<dyn MetricSource>;
+
+// 内置指标源：(id, 显示名称, 构造函数)
+const BUILTIN_SOURCES: &[(&str, &str, SourceFactory)] = &[
+    (METRIC_CPU, "CPU 占用", || Box::new(CpuSource::from_settings())),
+    (METRIC_MEMORY, "内存占用", || Box::new(MemorySource::new(MemoryKind::Ram))),
+    (METRIC_SWAP, "交换区占用", || Box::new(MemorySource::new(MemoryKind::Swap))),
+    (METRIC_NETWORK, "网络吞吐量", || Box::new(NetworkSource::from_settings())),
+    (METRIC_DISK, "磁盘繁忙度", || Box::new(DiskSource::from_settings())),
+    (METRIC_PSI_CPU, "CPU 压力 (PSI)", || Box::new(PsiSource::from_settings(PsiResource::Cpu))),
+    (METRIC_PSI_MEMORY, "内存压力 (PSI)", || Box::new(PsiSource::from_settings(PsiResource::Memory))),
+    (METRIC_PSI_IO, "IO 压力 (PSI)", || Box::new(PsiSource::from_settings(PsiResource::Io))),
+    (METRIC_CGROUP_CPU, "cgroup CPU 配额占用", || Box::new(CgroupSource::from_settings(CgroupResource::Cpu))),
+    (METRIC_CGROUP_MEMORY, "cgroup 内存配额占用", || Box::new(CgroupSource::from_settings(CgroupResource::Memory))),
+    (METRIC_PROCESS, "指定进程", || Box::new(ProcessSource::from_settings())),
+    (METRIC_THERMAL, "温度", || Box::new(ThermalSource::from_settings())),
+    (METRIC_BATTERY, "电池电量", || Box::new(BatterySource::from_settings(BatteryKind::Charge))),
+    (METRIC_BATTERY_DRAIN, "电池放电功率", || Box::new(BatterySource::from_settings(BatteryKind::Drain))),
+    (METRIC_LOAD, "负载均值", || Box::new(LoadSource::from_settings())),
+    (METRIC_COMMAND, "外部命令", || Box::new(CommandSource::from_settings())),
+    (METRIC_PROMETHEUS, "Prometheus 指标", || Box::new(PrometheusSource::from_settings())),
+    (METRIC_REPLAY, "轨迹回放", || Box::new(ReplaySource::from_settings())),
+    (METRIC_COMPOSITE, "综合指标（表达式）", || Box::new(CompositeSource::from_settings())),
+];
+
+// 指标源描述信息
+pub struct MetricInfo {
+    pub id: String,
+    pub name: String,
+}
+
+// 列出所有可选的指标源：内置指标源和当前未过期的推送指标
+pub fn available_metrics() -> Vec<MetricInfo> {
+    let builtin = BUILTIN_SOURCES.iter().map(|(id, name, _)| MetricInfo {
+        id: id.to_string(),
+        name: name.to_string(),
+    });
+    let pushed = push::pushed_names().into_iter().map(|name| MetricInfo {
+        id: format!("{}{}", push::PUSH_PREFIX, name),
+        name: format!("{}（推送）", name),
+    });
+    builtin.chain(pushed).collect()
+}
+
+// 根据 id 创建指标源
+pub fn create_source(id: &str) -> Option<Box<dyn MetricSource>> {
+    if let Some(name) = id.strip_prefix(push::PUSH_PREFIX) {
+        return Some(Box::new(PushSource::new(name)));
+    }
+    BUILTIN_SOURCES
+        .iter()
+        .find(|(source_id, _, _)| *source_id == id)
+        .map(|(_, _, create)| create())
+}
+
+// 指标源创建时读取的设置值，值变化后需要重新创建该指标源；
+// 综合指标的表达式可以引用任意指标，包含全部内置指标源的设置
+pub fn source_settings(id: &str) -> Vec<String> {
+    use crate::utils::*;
+    match id {
+        METRIC_CPU => vec![get_cpu_mode()],
+        METRIC_NETWORK => vec![get_net_interface(), get_net_ceiling_mbps().to_string()],
+        METRIC_DISK => vec![get_disk_device()],
+        METRIC_PSI_CPU | METRIC_PSI_MEMORY | METRIC_PSI_IO => vec![get_psi_window()],
+        METRIC_CGROUP_CPU | METRIC_CGROUP_MEMORY => vec![get_cgroup_path()],
+        METRIC_PROCESS => vec![get_process_target()],
+        METRIC_THERMAL => vec![get_thermal_sensor(), get_thermal_min_c().to_string(), get_thermal_max_c().to_string()],
+        METRIC_BATTERY | METRIC_BATTERY_DRAIN => vec![get_battery_max_watts().to_string()],
+        METRIC_LOAD => vec![get_load_window().to_string(), get_load_ceiling_percent().to_string()],
+        METRIC_COMMAND => vec![
+            get_command_line(),
+            get_command_parse(),
+            get_command_min().to_string(),
+            get_command_max().to_string(),
+            get_command_timeout_ms().to_string(),
+        ],
+        METRIC_PROMETHEUS => vec![get_prom_url(), get_prom_query(), get_prom_min().to_string(), get_prom_max().to_string()],
+        METRIC_REPLAY => vec![get_replay_path(), get_replay_speed().to_string()],
+        METRIC_COMPOSITE => std::iter::once(get_composite_expr())
+            .chain(
+                BUILTIN_SOURCES
+                    .iter()
+                    .filter(|(source_id, _, _)| *source_id != METRIC_COMPOSITE)
+                    .flat_map(|(source_id, _, _)| source_settings(source_id)),
+            )
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// 指标源的可选项，在托盘菜单中显示为子菜单，选中后写入对应的设置项
+pub struct MetricOptions {
+    pub title: &'static str,
+    pub setting: String,
+    pub current: String,
+    // (显示名称, 设置值)
+    pub choices: Vec<(String, String)>,
+}
+
+// 返回指定指标源的全部可选项：指标源特有的选项和平滑滤波
+pub fn metric_options(id: &str) -> Vec<MetricOptions> {
+    let mut options: Vec<MetricOptions> = source_options(id).into_iter().collect();
+    options.push(MetricOptions {
+        title: "平滑",
+        setting: crate::utils::metric_filter_setting(id),
+        current: crate::utils::get_metric_filter(id),
+        choices: filter::FILTER_PRESETS
+            .iter()
+            .map(|(label, spec)| (label.to_string(), spec.to_string()))
+            .collect(),
+    });
+    options
+}
+
+// 指标源特有的选项，没有时返回 None
+fn source_options(id: &str) -> Option<MetricOptions> {
+    match id {
+        METRIC_CPU => Some(MetricOptions {
+            title: "CPU 模式",
+            setting: "CpuMode".to_string(),
+            current: CpuMode::parse(&crate::utils::get_cpu_mode()).to_setting(),
+            choices: cpu_mode_choices(),
+        }),
+        METRIC_NETWORK => Some(MetricOptions {
+            title: "网络接口",
+            setting: "NetInterface".to_string(),
+            current: crate::utils::get_net_interface(),
+            choices: device_choices("全部接口", network::list_interfaces()),
+        }),
+        METRIC_DISK => Some(MetricOptions {
+            title: "磁盘设备",
+            setting: "DiskDevice".to_string(),
+            current: crate::utils::get_disk_device(),
+            choices: device_choices("任意磁盘", disk::list_disks()),
+        }),
+        METRIC_THERMAL => Some(MetricOptions {
+            title: "温度传感器",
+            setting: "ThermalSensor".to_string(),
+            current: crate::utils::get_thermal_sensor(),
+            choices: device_choices("最热的传感器", thermal::list_sensors()),
+        }),
+        METRIC_LOAD => Some(MetricOptions {
+            title: "负载窗口",
+            setting: "LoadWindow".to_string(),
+            current: crate::utils::get_load_window().to_string(),
+            choices: [1, 5, 15]
+                .iter()
+                .map(|w| (format!("{} 分钟", w), w.to_string()))
+                .collect(),
+        }),
+        METRIC_PSI_CPU | METRIC_PSI_MEMORY | METRIC_PSI_IO => Some(MetricOptions {
+            title: "PSI 统计窗口",
+            setting: "PsiWindow".to_string(),
+            current: crate::utils::get_psi_window(),
+            choices: ["some:avg10", "some:avg60", "full:avg10", "full:avg60"]
+                .iter()
+                .map(|w| (w.replace(':', " "), w.to_string()))
+                .collect(),
+        }),
+        METRIC_REPLAY => Some(MetricOptions {
+            title: "回放速度",
+            setting: "ReplaySpeed".to_string(),
+            current: crate::utils::get_replay_speed().to_string(),
+            choices: [1, 2, 4, 10]
+                .iter()
+                .map(|x| (format!("{} 倍速", x), x.to_string()))
+                .collect(),
+        }),
+        METRIC_COMPOSITE => Some(MetricOptions {
+            title: "综合表达式",
+            setting: "CompositeExpr".to_string(),
+            current: crate::utils::get_composite_expr(),
+            choices: COMPOSITE_PRESETS
+                .iter()
+                .map(|expr| (expr.to_string(), expr.to_string()))
+                .collect(),
+        }),
+        _ => None,
+    }
+}
+
+// 菜单中提供的综合指标表达式，其他表达式可直接写入设置项 CompositeExpr
+const COMPOSITE_PRESETS: &[&str] = &[
+    "max(cpu, mem)",
+    "max(cpu, mem, io)",
+    "0.7*cpu + 0.3*io",
+    "if(battery < 20, 100, cpu)",
+];
+
+// CPU 模式选项：平均、最繁忙核心、最繁忙 4 核平均以及每个核心
+fn cpu_mode_choices() -> Vec<(String, String)> {
+    let mut modes = vec![CpuMode::Average, CpuMode::Max, CpuMode::TopMean(4)];
+    modes.extend((0..cpu::logical_core_count()).map(CpuMode::Core));
+    modes
+        .into_iter()
+        .map(|mode| (mode.label(), mode.to_setting()))
+        .collect()
+}
+
+// 设备列表选项：首项为空值表示全部设备
+fn device_choices(all_label: &str, devices: Vec<String>) -> Vec<(String, String)> {
+    std::iter::once((all_label.to_string(), String::new()))
+        .chain(devices.into_iter().map(|d| (d.clone(), d)))
+        .collect()
+}
@@ -0,0 +1,109 @@
+// 网络指标源：按接口统计收发吞吐量，按设定的上限换算成动画速度
+
+use std::time::Instant;
+use sysinfo::{NetworkExt, NetworksExt, System, SystemExt};
+
+use super::MetricSource;
+
+const MB: f64 = 1024.0 * 1024.0;
+
+pub struct NetworkSource {
+    system: System,
+    // 为空表示统计全部接口
+    interface: String,
+    ceiling_mbps: f32,
+    name: String,
+    last_refresh: Instant,
+    rx_per_sec: f64,
+    tx_per_sec: f64,
+}
+
+impl NetworkSource {
+    pub fn new(interface: String, ceiling_mbps: u32) -> Self {
+        let mut system = System::new();
+        system.refresh_networks_list();
+
+        let name = if interface.is_empty() {
+            "网络".to_string()
+        } else {
+            format!("网络 ({})", interface)
+        };
+
+        NetworkSource {
+            system,
+            interface,
+            ceiling_mbps: ceiling_mbps.max(1) as f32,
+            name,
+            last_refresh: Instant::now(),
+            rx_per_sec: 0.0,
+            tx_per_sec: 0.0,
+        }
+    }
+
+    // 按设置创建
+    pub fn from_settings() -> Self {
+        NetworkSource::new(
+            crate::utils::get_net_interface(),
+            crate::utils::get_net_ceiling_mbps(),
+        )
+    }
+}
+
+// 列出当前系统的网络接口名称
+pub fn list_interfaces() -> Vec<String> {
+    let mut system = System::new();
+    system.refresh_networks_list();
+    let mut names: Vec<String> = system.networks().iter().map(|(name, _)| name.clone()).collect();
+    names.sort();
+    names
+}
+
+impl MetricSource for NetworkSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn unit(&self) -> &str {
+        "Mbit/s"
+    }
+
+    fn range(&self) -> (f32, f32) {
+        (0.0, self.ceiling_mbps)
+    }
+
+    fn sample(&mut self) -> Result<f32, String> {
+        self.system.refresh_networks();
+        let elapsed = self.last_refresh.elapsed().as_secs_f64().max(0.001);
+        self.last_refresh = Instant::now();
+
+        let mut found = false;
+        let mut rx: u64 = 0;
+        let mut tx: u64 = 0;
+        for (name, data) in self.system.networks().iter() {
+            if self.interface.is_empty() || *name == self.interface {
+                found = true;
+                rx += data.received();
+                tx += data.transmitted();
+            }
+        }
+
+        if !found {
+            // 接口可能是之后才出现的，重新获取接口列表
+            self.system.refresh_networks_list();
+            return Err(format!("找不到网络接口 {}", self.interface));
+        }
+
+        self.rx_per_sec = rx as f64 / elapsed;
+        self.tx_per_sec = tx as f64 / elapsed;
+
+        Ok(((self.rx_per_sec + self.tx_per_sec) * 8.0 / 1_000_000.0) as f32)
+    }
+
+    fn detail(&self) -> Option<String> {
+        Some(format!(
+            "接收 {:.2} MB/s，发送 {:.2} MB/s",
+            self.rx_per_sec / MB,
+            self.tx_per_sec / MB,
+        ))
+    }
+}
@@ -0,0 +1,185 @@
+// 进程指标源：跟踪指定 PID、按名称匹配的进程，或某个进程及其全部子孙进程的 CPU 占用
+//
+// 目标在设置中以字符串保存：pid:1234、name:<正则表达式>、tree:1234
+
+use std::collections::HashSet;
+use regex::Regex;
+use sysinfo::{Pid, PidExt, ProcessExt, System, SystemExt};
+
+use super::MetricSource;
+
+const GIB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+// 跟踪目标
+pub enum ProcessTarget {
+    Pid(u32),
+    Name(Regex),
+    Tree(u32),
+}
+
+impl ProcessTarget {
+    pub fn parse(s: &str) -> Result<ProcessTarget, String> {
+        let s = s.trim();
+        let parse_pid = |pid: &str| {
+            pid.trim()
+                .parse::<u32>()
+                .map_err(|_| format!("无效的 PID: {}", pid))
+        };
+
+        if let Some(pid) = s.strip_prefix("pid:") {
+            Ok(ProcessTarget::Pid(parse_pid(pid)?))
+        } else if let Some(pid) = s.strip_prefix("tree:") {
+            Ok(ProcessTarget::Tree(parse_pid(pid)?))
+        } else if let Some(pattern) = s.strip_prefix("name:") {
+            Regex::new(pattern)
+                .map(ProcessTarget::Name)
+                .map_err(|e| format!("无效的进程名正则: {}", e))
+        } else if s.is_empty() {
+            Err("未设置要跟踪的进程".to_string())
+        } else {
+            Err(format!("无法识别的进程目标: {}", s))
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            ProcessTarget::Pid(pid) => format!("进程 {}", pid),
+            ProcessTarget::Name(re) => format!("进程 {}", re.as_str()),
+            ProcessTarget::Tree(pid) => format!("进程树 {}", pid),
+        }
+    }
+}
+
+pub struct ProcessSource {
+    target: Result<ProcessTarget, String>,
+    system: System,
+    name: String,
+    cores: f32,
+    // 当前跟踪的进程（进程树模式下包含已被重新挂靠的子孙进程）
+    tracked: HashSet<Pid>,
+    // 是否曾经找到过目标进程，用于区分"尚未启动"和"已退出"
+    seen: bool,
+    count: usize,
+    memory: u64,
+}
+
+impl ProcessSource {
+    pub fn new(target: Result<ProcessTarget, String>) -> Self {
+        let name = match &target {
+            Ok(target) => target.describe(),
+            Err(_) => "进程".to_string(),
+        };
+
+        let mut system = System::new();
+        system.refresh_cpu();
+
+        let mut tracked = HashSet::new();
+        if let Ok(ProcessTarget::Tree(pid)) = &target {
+            tracked.insert(Pid::from_u32(*pid));
+        }
+
+        ProcessSource {
+            target,
+            cores: system.cpus().len().max(1) as f32,
+            system,
+            name,
+            tracked,
+            seen: false,
+            count: 0,
+            memory: 0,
+        }
+    }
+
+    // 按设置创建
+    pub fn from_settings() -> Self {
+        ProcessSource::new(ProcessTarget::parse(&crate::utils::get_process_target()))
+    }
+
+    // 根据目标收集本次要统计的进程
+    fn collect_pids(&mut self) -> Vec<Pid> {
+        let processes = self.system.processes();
+        match &self.target {
+            Ok(ProcessTarget::Pid(pid)) => {
+                let pid = Pid::from_u32(*pid);
+                processes.get(&pid).map(|_| vec![pid]).unwrap_or_default()
+            }
+            Ok(ProcessTarget::Name(re)) => processes
+                .iter()
+                .filter(|(_, p)| re.is_match(p.name()))
+                .map(|(pid, _)| *pid)
+                .collect(),
+            Ok(ProcessTarget::Tree(_)) => {
+                // 去掉已退出的进程，再把父进程在跟踪集合中的进程加入，直到不再变化
+                self.tracked.retain(|pid| processes.contains_key(pid));
+                loop {
+                    let children: Vec<Pid> = processes
+                        .iter()
+                        .filter(|(pid, p)| {
+                            !self.tracked.contains(pid)
+                                && p.parent().is_some_and(|parent| self.tracked.contains(&parent))
+                        })
+                        .map(|(pid, _)| *pid)
+                        .collect();
+                    if children.is_empty() {
+                        break;
+                    }
+                    self.tracked.extend(children);
+                }
+                self.tracked.iter().copied().collect()
+            }
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+impl MetricSource for ProcessSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn unit(&self) -> &str {
+        "%"
+    }
+
+    fn range(&self) -> (f32, f32) {
+        (0.0, 100.0)
+    }
+
+    fn sample(&mut self) -> Result<f32, String> {
+        if let Err(e) = &self.target {
+            return Err(e.clone());
+        }
+
+        self.system.refresh_processes();
+        let pids = self.collect_pids();
+        if pids.is_empty() {
+            return Err(if self.seen { "已退出" } else { "未找到进程" }.to_string());
+        }
+        self.seen = true;
+        self.count = pids.len();
+
+        let processes = self.system.processes();
+        let mut cpu = 0.0f32;
+        self.memory = 0;
+        for pid in &pids {
+            if let Some(process) = processes.get(pid) {
+                cpu += process.cpu_usage();
+                self.memory += process.memory();
+            }
+        }
+
+        // 进程占用率以单核为 100%，换算成整机百分比
+        Ok((cpu / self.cores).clamp(0.0, 100.0))
+    }
+
+    fn detail(&self) -> Option<String> {
+        if !self.seen {
+            return None;
+        }
+        let count = match &self.target {
+            Ok(ProcessTarget::Pid(_)) => String::new(),
+            _ => format!("，{} 个进程", self.count),
+        };
+        Some(format!("内存 {:.2} GB{}", self.memory as f64 / GIB, count))
+    }
+}
@@ -0,0 +1,574 @@
+// Prometheus / OpenMetrics 指标源：抓取本地 exporter 的文本格式指标，按名称和标签选择序列
+//
+// 选择器语法与 PromQL 相同的子集：
+//   node_load1
+//   node_network_receive_bytes_total{device="eth0"}
+//   http_requests_total{job=~"api|web", code!="200"}
+//   rate(node_cpu_seconds_total{mode!="idle"})      计数器每秒增量
+// 多个序列匹配时取总和。只支持 http://，不支持压缩和 TLS
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use regex::Regex;
+
+use super::MetricSource;
+
+const TIMEOUT: Duration = Duration::from_secs(3);
+// 带时间戳的样本超过该时长视为过期，与 Prometheus 的默认回溯窗口一致
+const STALE_AFTER_MS: f64 = 5.0 * 60.0 * 1000.0;
+
+// 标签匹配方式
+enum Matcher {
+    Equal(String),
+    NotEqual(String),
+    Match(Regex),
+    NotMatch(Regex),
+}
+
+impl Matcher {
+    fn matches(&self, value: &str) -> bool {
+        match self {
+            Matcher::Equal(v) => value == v,
+            Matcher::NotEqual(v) => value != v,
+            Matcher::Match(re) => re.is_match(value),
+            Matcher::NotMatch(re) => !re.is_match(value),
+        }
+    }
+}
+
+// 序列选择器
+struct Selector {
+    name: String,
+    // 缺少的标签按空字符串参与匹配
+    matchers: Vec<(String, Matcher)>,
+    rate: bool,
+}
+
+impl Selector {
+    fn parse(text: &str) -> Result<Selector, String> {
+        let text = text.trim();
+        let (text, rate) = match text.strip_prefix("rate(").and_then(|t| t.strip_suffix(')')) {
+            Some(inner) => (inner.trim(), true),
+            None => (text, false),
+        };
+
+        let (name, rest) = match text.find('{') {
+            Some(i) => (&text[..i], Some(&text[i..])),
+            None => (text, None),
+        };
+        let name = name.trim();
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':') {
+            return Err(format!("无效的指标名: {}", name));
+        }
+
+        let mut matchers = Vec::new();
+        if let Some(rest) = rest {
+            let (labels, tail) = parse_labels(rest)?;
+            if !tail.trim().is_empty() {
+                return Err(format!("选择器末尾有多余内容: {}", tail.trim()));
+            }
+            for (label, op, value) in labels {
+                let matcher = match op {
+                    "=" => Matcher::Equal(value),
+                    "!=" => Matcher::NotEqual(value),
+                    _ => {
+                        // 与 PromQL 相同，正则匹配整个标签值
+                        let re = Regex::new(&format!("^(?:{})$", value))
+                            .map_err(|e| format!("标签 {} 的正则无效: {}", label, e))?;
+                        if op == "=~" {
+                            Matcher::Match(re)
+                        } else {
+                            Matcher::NotMatch(re)
+                        }
+                    }
+                };
+                matchers.push((label, matcher));
+            }
+        }
+
+        Ok(Selector { name: name.to_string(), matchers, rate })
+    }
+
+    fn matches(&self, series: &Series) -> bool {
+        series.name == self.name
+            && self.matchers.iter().all(|(label, matcher)| {
+                let value = series
+                    .labels
+                    .iter()
+                    .find(|(l, _)| l == label)
+                    .map(|(_, v)| v.as_str())
+                    .unwrap_or("");
+                matcher.matches(value)
+            })
+    }
+}
+
+// 标签条件：(标签, 运算符, 值)
+type LabelTerm = (String, &'static str, String);
+
+// 解析 {a="1", b!~"x"} 形式的标签列表，返回各项条件和右括号之后的内容；
+// 样本行中的标签只使用 = 运算符
+fn parse_labels(text: &str) -> Result<(Vec<LabelTerm>, &str), String> {
+    let mut rest = text.strip_prefix('{').ok_or_else(|| "缺少 {".to_string())?;
+    let mut labels = Vec::new();
+
+    loop {
+        rest = rest.trim_start();
+        if let Some(after) = rest.strip_prefix('}') {
+            return Ok((labels, after));
+        }
+
+        let end = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .ok_or_else(|| "标签列表没有闭合".to_string())?;
+        let label = rest[..end].to_string();
+        if label.is_empty() {
+            return Err(format!("无效的标签: {}", rest));
+        }
+        rest = rest[end..].trim_start();
+
+        let op = ["=~", "!~", "!=", "="]
+            .into_iter()
+            .find(|op| rest.starts_with(op))
+            .ok_or_else(|| format!("标签 {} 缺少运算符", label))?;
+        rest = rest[op.len()..].trim_start();
+
+        let (value, after) = parse_quoted(rest).ok_or_else(|| format!("标签 {} 的值缺少引号", label))?;
+        labels.push((label, op, value));
+
+        rest = after.trim_start();
+        if let Some(after) = rest.strip_prefix(',') {
+            rest = after;
+        } else if !rest.starts_with('}') {
+            return Err("标签之间缺少逗号".to_string());
+        }
+    }
+}
+
+// 解析带转义的双引号字符串，返回 (内容, 剩余部分)
+fn parse_quoted(text: &str) -> Option<(String, &str)> {
+    let body = text.strip_prefix('"')?;
+    let mut value = String::new();
+    let mut chars = body.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &body[i + 1..])),
+            '\\' => match chars.next()?.1 {
+                'n' => value.push('\n'),
+                other => value.push(other),
+            },
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+// 一个样本
+struct Series {
+    name: String,
+    labels: Vec<(String, String)>,
+    value: f64,
+    // 毫秒时间戳
+    timestamp_ms: Option<f64>,
+}
+
+impl Series {
+    // 用于在两次抓取之间识别同一序列
+    fn key(&self) -> String {
+        let labels: Vec<String> = self.labels.iter().map(|(l, v)| format!("{}={:?}", l, v)).collect();
+        format!("{}{{{}}}", self.name, labels.join(","))
+    }
+}
+
+// 解析文本格式；OpenMetrics 的时间戳以秒为单位，Prometheus 文本格式以毫秒为单位
+fn parse_exposition(body: &str, openmetrics: bool) -> Vec<Series> {
+    let mut series = Vec::new();
+    for line in body.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let end = line.find(['{', ' ', '\t']).unwrap_or(line.len());
+        let name = line[..end].to_string();
+        let mut rest = &line[end..];
+
+        let mut labels = Vec::new();
+        if rest.starts_with('{') {
+            match parse_labels(rest) {
+                Ok((parsed, after)) => {
+                    labels = parsed.into_iter().map(|(l, _, v)| (l, v)).collect();
+                    rest = after;
+                }
+                Err(_) => continue,
+            }
+        }
+
+        let mut fields = rest.split_whitespace();
+        let value = match fields.next().and_then(parse_float) {
+            Some(value) => value,
+            None => continue,
+        };
+        let timestamp_ms = fields
+            .next()
+            .and_then(|t| t.parse::<f64>().ok())
+            .map(|t| if openmetrics { t * 1000.0 } else { t });
+
+        series.push(Series { name, labels, value, timestamp_ms });
+    }
+    series
+}
+
+fn parse_float(text: &str) -> Option<f64> {
+    match text {
+        "+Inf" | "Inf" => Some(f64::INFINITY),
+        "-Inf" => Some(f64::NEG_INFINITY),
+        "NaN" => Some(f64::NAN),
+        _ => text.parse().ok(),
+    }
+}
+
+pub struct PrometheusSource {
+    url: String,
+    selector: Result<Selector, String>,
+    name: String,
+    range: (f32, f32),
+    // 计算速率用的上一次抓取结果：序列 -> 数值
+    previous: HashMap<String, f64>,
+    previous_at: Option<Instant>,
+    matched: usize,
+}
+
+impl PrometheusSource {
+    pub fn new(url: &str, selector: &str, range: (f32, f32)) -> Self {
+        PrometheusSource {
+            url: url.trim().to_string(),
+            selector: Selector::parse(selector),
+            name: selector.trim().to_string(),
+            range,
+            previous: HashMap::new(),
+            previous_at: None,
+            matched: 0,
+        }
+    }
+
+    // 按设置创建
+    pub fn from_settings() -> Self {
+        PrometheusSource::new(
+            &crate::utils::get_prom_url(),
+            &crate::utils::get_prom_query(),
+            (crate::utils::get_prom_min(), crate::utils::get_prom_max()),
+        )
+    }
+}
+
+impl MetricSource for PrometheusSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn unit(&self) -> &str {
+        match &self.selector {
+            Ok(selector) if selector.rate => "/s",
+            _ => "",
+        }
+    }
+
+    fn range(&self) -> (f32, f32) {
+        self.range
+    }
+
+    fn sample(&mut self) -> Result<f32, String> {
+        self.selector.as_ref().map_err(|e| e.clone())?;
+        let (body, openmetrics) = http_get(&self.url)?;
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs_f64() * 1000.0)
+            .unwrap_or(0.0);
+        self.evaluate(&body, openmetrics, Instant::now(), now_ms)
+    }
+
+    fn detail(&self) -> Option<String> {
+        if self.matched == 0 {
+            return None;
+        }
+        Some(format!("{} 个序列，{}", self.matched, self.url))
+    }
+}
+
+impl PrometheusSource {
+    // 从一次抓取的正文计算选择器的值；scraped_at 用于计算速率，now_ms 为当前的毫秒时间戳，用于判断过期
+    fn evaluate(&mut self, body: &str, openmetrics: bool, scraped_at: Instant, now_ms: f64) -> Result<f32, String> {
+        let selector = self.selector.as_ref().map_err(|e| e.clone())?;
+        let all: Vec<Series> = parse_exposition(body, openmetrics)
+            .into_iter()
+            .filter(|s| selector.matches(s))
+            .collect();
+        // NaN 样本和时间戳过旧的样本视为过期
+        let current: Vec<&Series> = all
+            .iter()
+            .filter(|s| !s.value.is_nan() && s.timestamp_ms.is_none_or(|t| now_ms - t <= STALE_AFTER_MS))
+            .collect();
+        self.matched = current.len();
+
+        if current.is_empty() {
+            let had_series = !self.previous.is_empty();
+            self.previous.clear();
+            self.previous_at = None;
+            return Err(if !all.is_empty() {
+                "序列已过期".to_string()
+            } else if had_series {
+                "序列已消失".to_string()
+            } else {
+                "未找到匹配的序列".to_string()
+            });
+        }
+
+        let values: HashMap<String, f64> = current.iter().map(|s| (s.key(), s.value)).collect();
+        let result = if selector.rate {
+            let rate = self.previous_at.map(|at| {
+                let elapsed = scraped_at.duration_since(at).as_secs_f64().max(0.001);
+                values
+                    .iter()
+                    .filter_map(|(key, value)| {
+                        let prev = self.previous.get(key)?;
+                        // 计数器减小说明进程重启后从 0 开始计数
+                        let delta = if value >= prev { value - prev } else { *value };
+                        Some(delta / elapsed)
+                    })
+                    .sum::<f64>()
+            });
+            self.previous = values;
+            self.previous_at = Some(scraped_at);
+            // 第一次抓取没有基准，速率记为 0
+            rate.unwrap_or(0.0)
+        } else {
+            let sum = values.values().sum();
+            self.previous = values;
+            sum
+        };
+
+        if !result.is_finite() {
+            return Err("序列的值不是有限数".to_string());
+        }
+        Ok(result as f32)
+    }
+}
+
+// 发送 HTTP GET 请求，返回 (响应正文, 是否为 OpenMetrics 格式)
+fn http_get(url: &str) -> Result<(String, bool), String> {
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| format!("只支持 http:// 地址: {}", url))?;
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    let host_port = if authority.contains(':') {
+        authority.to_string()
+    } else {
+        format!("{}:80", authority)
+    };
+
+    let addr = host_port
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+        .ok_or_else(|| format!("无法解析地址: {}", authority))?;
+    let mut stream = TcpStream::connect_timeout(&addr, TIMEOUT).map_err(|e| format!("无法连接 {}: {}", authority, e))?;
+    let _ = stream.set_read_timeout(Some(TIMEOUT));
+    let _ = stream.set_write_timeout(Some(TIMEOUT));
+
+    // 使用 HTTP/1.0，服务器在发送完正文后关闭连接
+    let request = format!(
+        "GET {} HTTP/1.0\r\nHost: {}\r\nAccept: application/openmetrics-text;version=1.0.0,text/plain;version=0.0.4;q=0.5\r\nAccept-Encoding: identity\r\nUser-Agent: RunCat\r\n\r\n",
+        path, authority
+    );
+    stream.write_all(request.as_bytes()).map_err(|e| format!("发送请求失败: {}", e))?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).map_err(|e| format!("读取响应失败: {}", e))?;
+    parse_response(&response)
+}
+
+// 解析完整的 HTTP 响应，返回 (响应正文, 是否为 OpenMetrics 格式)
+fn parse_response(response: &[u8]) -> Result<(String, bool), String> {
+    let header_end = response
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or_else(|| "HTTP 响应不完整".to_string())?;
+    let head = String::from_utf8_lossy(&response[..header_end]).to_string();
+    let body = &response[header_end + 4..];
+
+    let mut lines = head.lines();
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| "HTTP 响应格式无法识别".to_string())?;
+    if status != 200 {
+        return Err(format!("HTTP 状态码 {}", status));
+    }
+
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(k, v)| (k.trim().to_ascii_lowercase(), v.trim().to_ascii_lowercase()))
+        .collect();
+    let header = |name: &str| headers.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str());
+
+    let body = if header("transfer-encoding") == Some("chunked") {
+        decode_chunked(body)?
+    } else {
+        body.to_vec()
+    };
+    let openmetrics = header("content-type").is_some_and(|t| t.starts_with("application/openmetrics-text"));
+
+    Ok((String::from_utf8_lossy(&body).to_string(), openmetrics))
+}
+
+fn decode_chunked(mut data: &[u8]) -> Result<Vec<u8>, String> {
+    let invalid = || "分块传输编码格式无效".to_string();
+    let mut body = Vec::new();
+    loop {
+        let line_end = data.windows(2).position(|w| w == b"\r\n").ok_or_else(invalid)?;
+        let size_line = String::from_utf8_lossy(&data[..line_end]);
+        let size_hex = size_line.split(';').next().unwrap_or("").trim();
+        let size = usize::from_str_radix(size_hex, 16).map_err(|_| invalid())?;
+        data = &data[line_end + 2..];
+        if size == 0 {
+            return Ok(body);
+        }
+        if data.len() < size {
+            return Err(invalid());
+        }
+        body.extend_from_slice(&data[..size]);
+        data = data.get(size + 2..).ok_or_else(invalid)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPOSITION: &str = r#"# HELP http_requests_total Requests.
+# TYPE http_requests_total counter
+http_requests_total{job="api",code="200"} 100
+http_requests_total{job="web",code="500"} 7 1700000000000
+http_requests_total{job="batch",code="200"} 3
+node_load1 1.5
+label_escapes{path="C:\\temp",msg="say \"hi\"\nbye"} 2
+broken{job="api" 5
+"#;
+
+    fn source(selector: &str) -> PrometheusSource {
+        PrometheusSource::new("http://127.0.0.1:9100/metrics", selector, (0.0, 100.0))
+    }
+
+    fn sum(selector: &str) -> f32 {
+        let selector = Selector::parse(selector).unwrap();
+        parse_exposition(EXPOSITION, false)
+            .iter()
+            .filter(|s| selector.matches(s))
+            .map(|s| s.value as f32)
+            .sum()
+    }
+
+    #[test]
+    fn parses_samples_and_skips_comments() {
+        let series = parse_exposition(EXPOSITION, false);
+        assert_eq!(series.len(), 5);
+        assert_eq!(series[0].labels, [("job".to_string(), "api".to_string()), ("code".to_string(), "200".to_string())]);
+        assert_eq!(series[1].timestamp_ms, Some(1700000000000.0));
+        assert_eq!(series[3].name, "node_load1");
+    }
+
+    #[test]
+    fn openmetrics_timestamps_are_seconds() {
+        let series = parse_exposition("up 1 1700000000.5\n", true);
+        assert_eq!(series[0].timestamp_ms, Some(1700000000500.0));
+    }
+
+    #[test]
+    fn label_escapes() {
+        let series = parse_exposition(EXPOSITION, false);
+        let labels = &series[4].labels;
+        assert_eq!(labels[0].1, "C:\\temp");
+        assert_eq!(labels[1].1, "say \"hi\"\nbye");
+    }
+
+    #[test]
+    fn special_values() {
+        let series = parse_exposition("a +Inf\nb -Inf\nc NaN\n", false);
+        assert_eq!(series[0].value, f64::INFINITY);
+        assert_eq!(series[1].value, f64::NEG_INFINITY);
+        assert!(series[2].value.is_nan());
+    }
+
+    #[test]
+    fn label_matchers() {
+        assert_eq!(sum("http_requests_total"), 110.0);
+        assert_eq!(sum(r#"http_requests_total{job="api"}"#), 100.0);
+        assert_eq!(sum(r#"http_requests_total{code!="200"}"#), 7.0);
+        assert_eq!(sum(r#"http_requests_total{job=~"api|web"}"#), 107.0);
+        assert_eq!(sum(r#"http_requests_total{job!~"a.*"}"#), 10.0);
+        // 正则匹配整个标签值
+        assert_eq!(sum(r#"http_requests_total{job=~"ap"}"#), 0.0);
+        // 缺少的标签按空字符串匹配
+        assert_eq!(sum(r#"node_load1{instance=""}"#), 1.5);
+    }
+
+    #[test]
+    fn invalid_selectors() {
+        assert!(Selector::parse("").is_err());
+        assert!(Selector::parse("bad-name").is_err());
+        assert!(Selector::parse(r#"up{job="a""#).is_err());
+        assert!(Selector::parse(r#"up{job=a}"#).is_err());
+        assert!(Selector::parse(r#"up{job="a" code="b"}"#).is_err());
+        assert!(Selector::parse(r#"up{job=~"("}"#).is_err());
+        assert!(Selector::parse(r#"up{job="a"} x"#).is_err());
+        assert!(Selector::parse("rate(up)").unwrap().rate);
+    }
+
+    #[test]
+    fn rate_handles_counter_reset() {
+        let mut source = source("rate(requests_total)");
+        let start = Instant::now();
+        assert_eq!(source.evaluate("requests_total 100\n", false, start, 0.0), Ok(0.0));
+        assert_eq!(source.evaluate("requests_total 120\n", false, start + Duration::from_secs(2), 0.0), Ok(10.0));
+        // 计数器从 0 重新开始，增量按当前值计算
+        assert_eq!(source.evaluate("requests_total 6\n", false, start + Duration::from_secs(4), 0.0), Ok(3.0));
+    }
+
+    #[test]
+    fn stale_and_missing_series() {
+        let now_ms = 1700000000000.0 + STALE_AFTER_MS + 1.0;
+        let mut source = source("up");
+        assert_eq!(source.evaluate("up 1 1700000000000\n", false, Instant::now(), now_ms), Err("序列已过期".to_string()));
+        assert_eq!(source.evaluate("up NaN\n", false, Instant::now(), now_ms), Err("序列已过期".to_string()));
+        assert_eq!(source.evaluate("up 1\n", false, Instant::now(), now_ms), Ok(1.0));
+        assert_eq!(source.evaluate("other 1\n", false, Instant::now(), now_ms), Err("序列已消失".to_string()));
+        assert_eq!(source.evaluate("other 1\n", false, Instant::now(), now_ms), Err("未找到匹配的序列".to_string()));
+    }
+
+    #[test]
+    fn chunked_response() {
+        let response = b"HTTP/1.1 200 OK\r\nContent-Type: application/openmetrics-text; version=1.0.0\r\nTransfer-Encoding: chunked\r\n\r\n5;ext=1\r\nup 1\n\r\n6\r\n# EOF\n\r\n0\r\n\r\n";
+        assert_eq!(parse_response(response), Ok(("up 1\n# EOF\n".to_string(), true)));
+    }
+
+    #[test]
+    fn malformed_chunks() {
+        assert!(decode_chunked(b"zz\r\nup 1\r\n0\r\n\r\n").is_err());
+        assert!(decode_chunked(b"ffffffffffffffffffff\r\nup 1\r\n").is_err());
+        assert!(decode_chunked(b"10\r\nup 1\r\n0\r\n\r\n").is_err());
+        assert!(decode_chunked(b"4\r\nup 1").is_err());
+    }
+
+    #[test]
+    fn http_status_and_plain_body() {
+        assert_eq!(parse_response(b"HTTP/1.0 404 Not Found\r\n\r\n"), Err("HTTP 状态码 404".to_string()));
+        assert_eq!(parse_response(b"HTTP/1.0 200 OK\r\nContent-Type: text/plain\r\n\r\nup 1\n"), Ok(("up 1\n".to_string(), false)));
+        assert!(parse_response(b"HTTP/1.0 200 OK\r\n").is_err());
+    }
+}
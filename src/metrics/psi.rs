@@ -0,0 +1,154 @@
+// Linux 压力阻塞信息（PSI）指标源：读取 /proc/pressure/{cpu,memory,io}
+//
+// 文件格式：
+//   some avg10=0.12 avg60=0.05 avg300=0.01 total=123456
+//   full avg10=0.00 avg60=0.00 avg300=0.00 total=0
+
+use super::MetricSource;
+
+// 统计的资源类型
+#[derive(Clone, Copy, PartialEq)]
+pub enum PsiResource {
+    Cpu,
+    Memory,
+    Io,
+}
+
+impl PsiResource {
+    fn file_name(self) -> &'static str {
+        match self {
+            PsiResource::Cpu => "cpu",
+            PsiResource::Memory => "memory",
+            PsiResource::Io => "io",
+        }
+    }
+}
+
+// 一行 PSI 记录中的平均值
+#[derive(Clone, Copy, Default)]
+struct PsiLine {
+    avg10: f32,
+    avg60: f32,
+    avg300: f32,
+}
+
+pub struct PsiSource {
+    resource: PsiResource,
+    name: String,
+    // 使用 "full" 行还是 "some" 行
+    full: bool,
+    // 使用的平均窗口：10、60 或 300 秒
+    window: u32,
+    some: Option<PsiLine>,
+    full_line: Option<PsiLine>,
+}
+
+impl PsiSource {
+    pub fn new(resource: PsiResource, selector: &str) -> Self {
+        let (line, window) = selector.split_once(':').unwrap_or(("some", "avg10"));
+        let window = match window {
+            "avg60" => 60,
+            "avg300" => 300,
+            _ => 10,
+        };
+
+        PsiSource {
+            resource,
+            name: format!("PSI {} ({} avg{})", resource.file_name(), line, window),
+            full: line == "full",
+            window,
+            some: None,
+            full_line: None,
+        }
+    }
+
+    // 按设置创建
+    pub fn from_settings(resource: PsiResource) -> Self {
+        PsiSource::new(resource, &crate::utils::get_psi_window())
+    }
+}
+
+impl MetricSource for PsiSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn unit(&self) -> &str {
+        "%"
+    }
+
+    fn range(&self) -> (f32, f32) {
+        (0.0, 100.0)
+    }
+
+    fn sample(&mut self) -> Result<f32, String> {
+        let path = format!("/proc/pressure/{}", self.resource.file_name());
+        let content = std::fs::read_to_string(&path)
+            .map_err(|_| format!("内核不支持 PSI（无法读取 {}）", path))?;
+
+        let (some, full) = parse_psi(&content)?;
+        self.some = Some(some);
+        self.full_line = full;
+
+        let line = if self.full {
+            full.ok_or_else(|| format!("{} 中没有 full 行", path))?
+        } else {
+            some
+        };
+
+        Ok(match self.window {
+            60 => line.avg60,
+            300 => line.avg300,
+            _ => line.avg10,
+        })
+    }
+
+    fn detail(&self) -> Option<String> {
+        let some = self.some?;
+        let mut detail = format!("some {:.2}/{:.2}/{:.2}", some.avg10, some.avg60, some.avg300);
+        if let Some(full) = self.full_line {
+            detail.push_str(&format!("，full {:.2}/{:.2}/{:.2}", full.avg10, full.avg60, full.avg300));
+        }
+        Some(detail)
+    }
+}
+
+// 解析 PSI 文件内容，返回 (some 行, full 行)；旧内核的 cpu 文件没有 full 行
+fn parse_psi(content: &str) -> Result<(PsiLine, Option<PsiLine>), String> {
+    let mut some = None;
+    let mut full = None;
+
+    for line in content.lines() {
+        let mut parts = line.split_whitespace();
+        let kind = match parts.next() {
+            Some(kind) => kind,
+            None => continue,
+        };
+
+        let mut values = PsiLine::default();
+        for part in parts {
+            let (key, value) = match part.split_once('=') {
+                Some(kv) => kv,
+                None => continue,
+            };
+            let value = value.parse::<f32>().unwrap_or(0.0);
+            match key {
+                "avg10" => values.avg10 = value,
+                "avg60" => values.avg60 = value,
+                "avg300" => values.avg300 = value,
+                _ => {}
+            }
+        }
+
+        match kind {
+            "some" => some = Some(values),
+            "full" => full = Some(values),
+            _ => {}
+        }
+    }
+
+    match some {
+        Some(some) => Ok((some, full)),
+        None => Err("PSI 数据格式无法识别".to_string()),
+    }
+}
@@ -0,0 +1,294 @@
+// 推送指标：其他程序通过本地套接字（Linux 为 UNIX 域套接字，Windows 为命名管道）写入数值
+//
+// 行协议，每行一条：
+//   metric_name value [ttl]
+// value 为 0-100 的数值；ttl 为有效期（秒），省略时使用设置 PushTtlSecs，为 0 或超出可表示的范围时永不过期。
+// 推送过的指标以 push:<名称> 出现在「监控指标」菜单中，过期后从菜单中移除
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::MetricSource;
+
+pub const PUSH_PREFIX: &str = "push:";
+
+// 一行推送数据的最大长度（字节），超过时断开该连接，避免无换行的数据占满内存
+const MAX_LINE_BYTES: usize = 1024;
+
+// 已推送的数值
+struct Pushed {
+    value: f32,
+    expires: Option<Instant>,
+}
+
+static PUSHED: Mutex<Option<HashMap<String, Pushed>>> = Mutex::new(None);
+
+// 解析一行推送数据并保存
+fn handle_line(line: &str) -> Result<(), String> {
+    let mut parts = line.split_whitespace();
+    let name = match parts.next() {
+        Some(name) => name,
+        // 空行
+        None => return Ok(()),
+    };
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')) {
+        return Err(format!("无效的指标名: {}", name));
+    }
+
+    let value = parts
+        .next()
+        .and_then(|v| v.parse::<f32>().ok())
+        .filter(|v| v.is_finite())
+        .ok_or_else(|| format!("{}: 缺少数值", name))?;
+    let ttl = match parts.next() {
+        Some(ttl) => ttl
+            .parse::<f32>()
+            .ok()
+            .filter(|t| t.is_finite() && *t >= 0.0)
+            .ok_or_else(|| format!("{}: 无效的有效期 {}", name, ttl))?,
+        None => crate::utils::get_push_ttl_secs() as f32,
+    };
+    if parts.next().is_some() {
+        return Err(format!("{}: 多余的字段", name));
+    }
+
+    // 有效期过长，超出 Duration 或 Instant 的表示范围时视为永不过期
+    let expires = (ttl > 0.0)
+        .then(|| Duration::try_from_secs_f32(ttl).ok().and_then(|ttl| Instant::now().checked_add(ttl)))
+        .flatten();
+    PUSHED
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(name.to_string(), Pushed { value, expires });
+    Ok(())
+}
+
+// 读取一个未过期的推送值，顺便清除所有已过期的数值
+fn read_pushed(name: &str) -> Option<f32> {
+    let mut pushed = PUSHED.lock().unwrap();
+    let pushed = pushed.get_or_insert_with(HashMap::new);
+    let now = Instant::now();
+    pushed.retain(|_, p| p.expires.is_none_or(|e| e > now));
+    pushed.get(name).map(|p| p.value)
+}
+
+// 当前未过期的推送指标名称，按名称排序
+pub fn pushed_names() -> Vec<String> {
+    let mut pushed = PUSHED.lock().unwrap();
+    let pushed = pushed.get_or_insert_with(HashMap::new);
+    let now = Instant::now();
+    pushed.retain(|_, p| p.expires.is_none_or(|e| e > now));
+    let mut names: Vec<String> = pushed.keys().cloned().collect();
+    names.sort();
+    names
+}
+
+pub struct PushSource {
+    name: String,
+    // 是否收到过数值，用于区分"尚未推送"和"已过期"
+    seen: bool,
+}
+
+impl PushSource {
+    pub fn new(name: &str) -> Self {
+        PushSource { name: name.to_string(), seen: false }
+    }
+}
+
+impl MetricSource for PushSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn unit(&self) -> &str {
+        ""
+    }
+
+    fn range(&self) -> (f32, f32) {
+        (0.0, 100.0)
+    }
+
+    fn sample(&mut self) -> Result<f32, String> {
+        match read_pushed(&self.name) {
+            Some(value) => {
+                self.seen = true;
+                Ok(value)
+            }
+            None if self.seen => Err("已过期".to_string()),
+            None => Err("尚未收到推送".to_string()),
+        }
+    }
+}
+
+// 在后台线程中监听推送；地址被其他 RunCat 实例占用等错误只输出，不影响其他功能
+pub fn start_push_listener() {
+    let address = crate::utils::get_push_address();
+    std::thread::spawn(move || {
+        if let Err(e) = listen(&address) {
+            eprintln!("推送监听失败 ({}): {}", address, e);
+        }
+    });
+}
+
+// 逐行读取一个连接中的推送数据
+fn read_connection(reader: impl std::io::Read) {
+    use std::io::{BufRead, Read};
+
+    let mut reader = std::io::BufReader::new(reader);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        match (&mut reader).take(MAX_LINE_BYTES as u64 + 1).read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        if line.len() > MAX_LINE_BYTES && !line.ends_with(b"\n") {
+            eprintln!("推送数据无效: 一行超过 {} 字节，断开连接", MAX_LINE_BYTES);
+            break;
+        }
+        if let Err(e) = handle_line(&String::from_utf8_lossy(&line)) {
+            eprintln!("推送数据无效: {}", e);
+        }
+    }
+}
+
+// 默认地址：$XDG_RUNTIME_DIR/runcat.sock，没有时使用 /tmp/runcat-<用户>.sock
+#[cfg(unix)]
+pub fn default_push_address() -> String {
+    let non_empty = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+    match (non_empty("XDG_RUNTIME_DIR"), non_empty("USER")) {
+        (Some(dir), _) => format!("{}/runcat.sock", dir.trim_end_matches('/')),
+        (None, Some(user)) => format!("/tmp/runcat-{}.sock", user),
+        (None, None) => "/tmp/runcat.sock".to_string(),
+    }
+}
+
+#[cfg(unix)]
+fn listen(path: &str) -> Result<(), String> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    // 能连上说明另一个实例正在监听；否则是上次遗留的套接字文件，删除后重新绑定。
+    // 只删除套接字，地址误设为普通文件等情况时报错
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            return Err("该路径已存在且不是套接字".to_string());
+        }
+        if UnixStream::connect(path).is_ok() {
+            return Err("地址已被占用".to_string());
+        }
+        std::fs::remove_file(path).map_err(|e| format!("无法删除遗留的套接字: {}", e))?;
+    }
+
+    // 套接字只允许当前用户连接。umask 是进程级的，不能为绑定临时修改，绑定后再设置权限；
+    // 设置失败时删除套接字，不以默认权限监听
+    let listener = UnixListener::bind(path).map_err(|e| e.to_string())?;
+    if let Err(e) = std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600)) {
+        let _ = std::fs::remove_file(path);
+        return Err(format!("无法设置套接字权限: {}", e));
+    }
+    for stream in listener.incoming().flatten() {
+        std::thread::spawn(move || read_connection(stream));
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+pub fn default_push_address() -> String {
+    r"\\.\pipe\runcat".to_string()
+}
+
+#[cfg(windows)]
+fn listen(name: &str) -> Result<(), String> {
+    use std::fs::File;
+    use std::os::windows::io::FromRawHandle;
+    use std::ptr::null_mut;
+    use winapi::shared::winerror::ERROR_PIPE_CONNECTED;
+    use winapi::um::errhandlingapi::GetLastError;
+    use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+    use winapi::um::namedpipeapi::{ConnectNamedPipe, CreateNamedPipeW};
+    use winapi::um::winbase::{
+        FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_INBOUND, PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS,
+        PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+    };
+
+    let name_w = crate::utils::to_wide_null(name);
+    let mut first = true;
+    loop {
+        // 第一个管道实例要求独占，避免与其他 RunCat 实例共用同一个管道名
+        let flags = PIPE_ACCESS_INBOUND | if first { FILE_FLAG_FIRST_PIPE_INSTANCE } else { 0 };
+        let pipe = unsafe {
+            CreateNamedPipeW(
+                name_w.as_ptr(),
+                flags,
+                PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                PIPE_UNLIMITED_INSTANCES,
+                0,
+                4096,
+                0,
+                null_mut(),
+            )
+        };
+        if pipe == INVALID_HANDLE_VALUE {
+            return Err(format!("无法创建命名管道 (错误 {})", unsafe { GetLastError() }));
+        }
+        first = false;
+
+        let connected = unsafe { ConnectNamedPipe(pipe, null_mut()) } != 0 || unsafe { GetLastError() } == ERROR_PIPE_CONNECTED;
+        if !connected {
+            unsafe { CloseHandle(pipe) };
+            continue;
+        }
+
+        // 连接交给单独的线程读取，File 在读取结束后关闭管道句柄
+        let file = unsafe { File::from_raw_handle(pipe as _) };
+        std::thread::spawn(move || read_connection(file));
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn default_push_address() -> String {
+    String::new()
+}
+
+#[cfg(not(any(unix, windows)))]
+fn listen(_address: &str) -> Result<(), String> {
+    Err("当前系统不支持推送指标".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_lines() {
+        assert_eq!(handle_line(""), Ok(()));
+        assert_eq!(handle_line("build.progress 40 0"), Ok(()));
+        assert_eq!(read_pushed("build.progress"), Some(40.0));
+        assert!(handle_line("bad/name 1 0").is_err());
+        assert!(handle_line("queue").is_err());
+        assert!(handle_line("queue 1 -5").is_err());
+        assert!(handle_line("queue 1 0 extra").is_err());
+
+        // 超出表示范围的有效期视为永不过期
+        assert_eq!(handle_line("x 1 1e30"), Ok(()));
+        assert_eq!(read_pushed("x"), Some(1.0));
+        assert!(PUSHED.lock().unwrap().as_ref().unwrap()["x"].expires.is_none());
+        assert_eq!(handle_line("y 2 1e15"), Ok(()));
+        assert_eq!(read_pushed("y"), Some(2.0));
+    }
+
+    #[test]
+    fn overlong_line_closes_connection() {
+        let mut data = format!("long_line {}", "1".repeat(MAX_LINE_BYTES));
+        data.push_str("\nafter_long 5 0\n");
+        read_connection(data.as_bytes());
+        assert_eq!(read_pushed("after_long"), None);
+
+        read_connection("first 1 0\r\nsecond 2 0".as_bytes());
+        assert_eq!(read_pushed("first"), Some(1.0));
+        assert_eq!(read_pushed("second"), Some(2.0));
+    }
+}
@@ -0,0 +1,114 @@
+// 温度指标源：读取传感器温度（Linux 为 hwmon，Windows 为 WMI），按设定的温度区间换算成动画速度
+//
+// 传感器提供临界温度时，以临界温度作为全速的上限，并在达到临界温度时在提示中警告
+
+use sysinfo::{ComponentExt, System, SystemExt};
+
+use super::MetricSource;
+
+pub struct ThermalSource {
+    system: System,
+    // 为空表示取最热的传感器
+    sensor: String,
+    name: String,
+    min_c: f32,
+    max_c: f32,
+    // 最近一次采样所用传感器的 (名称, 温度, 临界温度)
+    last: Option<(String, f32, Option<f32>)>,
+}
+
+impl ThermalSource {
+    pub fn new(sensor: String, min_c: u32, max_c: u32) -> Self {
+        let mut system = System::new();
+        system.refresh_components_list();
+
+        let name = if sensor.is_empty() {
+            "温度".to_string()
+        } else {
+            format!("温度 ({})", sensor)
+        };
+
+        ThermalSource {
+            system,
+            sensor,
+            name,
+            min_c: min_c as f32,
+            max_c: (max_c.max(min_c + 1)) as f32,
+            last: None,
+        }
+    }
+
+    // 按设置创建
+    pub fn from_settings() -> Self {
+        ThermalSource::new(
+            crate::utils::get_thermal_sensor(),
+            crate::utils::get_thermal_min_c(),
+            crate::utils::get_thermal_max_c(),
+        )
+    }
+}
+
+// 列出当前系统的温度传感器名称
+pub fn list_sensors() -> Vec<String> {
+    let mut system = System::new();
+    system.refresh_components_list();
+    let mut names: Vec<String> = system.components().iter().map(|c| c.label().to_string()).collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+impl MetricSource for ThermalSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn unit(&self) -> &str {
+        "°C"
+    }
+
+    fn range(&self) -> (f32, f32) {
+        // 临界温度低于设定上限时，提前在临界温度处达到全速
+        let max = match self.last {
+            Some((_, _, Some(critical))) if critical > self.min_c => critical.min(self.max_c),
+            _ => self.max_c,
+        };
+        (self.min_c, max)
+    }
+
+    fn sample(&mut self) -> Result<f32, String> {
+        self.system.refresh_components();
+
+        let component = self
+            .system
+            .components()
+            .iter()
+            .filter(|c| self.sensor.is_empty() || c.label() == self.sensor)
+            .filter(|c| c.temperature().is_finite())
+            .max_by(|a, b| a.temperature().total_cmp(&b.temperature()));
+
+        let component = match component {
+            Some(component) => component,
+            None if self.sensor.is_empty() => return Err("未找到温度传感器".to_string()),
+            None => return Err(format!("找不到传感器 {}", self.sensor)),
+        };
+
+        let critical = component.critical().filter(|c| c.is_finite() && *c > 0.0);
+        self.last = Some((component.label().to_string(), component.temperature(), critical));
+
+        Ok(component.temperature())
+    }
+
+    fn detail(&self) -> Option<String> {
+        let (label, temperature, critical) = self.last.as_ref()?;
+        let mut detail = label.clone();
+        if let Some(critical) = critical {
+            if temperature >= critical {
+                detail.push_str(&format!("\n已达到临界温度 {:.0}°C！", critical));
+            } else {
+                detail.push_str(&format!("，临界 {:.0}°C", critical));
+            }
+        }
+        Some(detail)
+    }
+}
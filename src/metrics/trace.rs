@@ -0,0 +1,422 @@
+// 指标轨迹的录制和回放
+//
+// 轨迹文件为 UTF-8 文本，每行一条记录，字段以空格分隔：
+//   runcat-trace 2 <开始时间>          首行：格式标识、版本号、录制开始的 Unix 时间（毫秒）
+//   d <id>                              之后的样本中驱动动画的指标，录制开始和切换指标时写出；
+//                                         其他指标的样本来自托盘提示中附加显示的指标，回放时只播放驱动动画的指标
+//   m <id> <最小值> <最大值> <单位> <名称>   指标说明，出现在该指标的第一条样本之前，设置变化导致名称、单位或范围改变时重新写出；
+//                                         单位为空时写作 -，名称可含空格
+//   s <毫秒> <id> <数值> [charging]      样本：距开始的毫秒数、指标 id、经过平滑后的数值，充电状态时带 charging
+//   e <毫秒> <id> <错误信息>             采样失败
+// 以 # 开头的行和空行被忽略。版本号只在格式不兼容时增加，读取时拒绝不认识的版本；
+// 版本 1 没有 d 行，只录制驱动动画的指标，仍可读取
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use super::{MetricSource, MetricState};
+
+const MAGIC: &str = "runcat-trace";
+const VERSION: u32 = 2;
+
+// 轨迹录制器：采样线程每次采样后写入一条记录
+pub struct TraceRecorder {
+    writer: BufWriter<File>,
+    started: Instant,
+    // 各指标最近一次写出的说明
+    declared: HashMap<String, TraceMetric>,
+    // 最近一次写出的驱动动画的指标
+    driving: Option<String>,
+}
+
+impl TraceRecorder {
+    pub fn create(path: &Path) -> Result<TraceRecorder, String> {
+        let file = File::create(path).map_err(|e| format!("无法创建轨迹文件 {}: {}", path.display(), e))?;
+        let mut writer = BufWriter::new(file);
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0);
+        writeln!(writer, "{} {} {}", MAGIC, VERSION, now_ms).map_err(|e| e.to_string())?;
+
+        Ok(TraceRecorder {
+            writer,
+            started: Instant::now(),
+            declared: HashMap::new(),
+            driving: None,
+        })
+    }
+
+    // 写入一次采样结果，driving 表示该指标正在驱动动画；每条记录立即写入磁盘，程序异常退出时也能保留已录制的部分
+    pub fn record(&mut self, id: &str, source: &dyn MetricSource, result: &Result<f32, String>, driving: bool) -> std::io::Result<()> {
+        if driving && self.driving.as_deref() != Some(id) {
+            writeln!(self.writer, "d {}", id)?;
+            self.driving = Some(id.to_string());
+        }
+        let metric = TraceMetric {
+            name: one_line(source.name()),
+            unit: source.unit().to_string(),
+            range: source.range(),
+        };
+        if self.declared.get(id) != Some(&metric) {
+            let (min, max) = metric.range;
+            let unit = if metric.unit.is_empty() { "-" } else { &metric.unit };
+            writeln!(self.writer, "m {} {} {} {} {}", id, min, max, unit, metric.name)?;
+            self.declared.insert(id.to_string(), metric);
+        }
+
+        let ms = self.started.elapsed().as_millis();
+        match result {
+            Ok(value) if source.state() == MetricState::Charging => writeln!(self.writer, "s {} {} {} charging", ms, id, value)?,
+            Ok(value) => writeln!(self.writer, "s {} {} {}", ms, id, value)?,
+            Err(e) => writeln!(self.writer, "e {} {} {}", ms, id, one_line(e))?,
+        }
+        self.writer.flush()
+    }
+}
+
+fn one_line(text: &str) -> String {
+    text.replace(['\r', '\n'], " ")
+}
+
+// 轨迹中的指标说明
+#[derive(PartialEq)]
+struct TraceMetric {
+    name: String,
+    unit: String,
+    range: (f32, f32),
+}
+
+// 一条样本记录
+struct TraceRecord {
+    ms: u64,
+    metric: String,
+    // 记录时有效的指标说明在 Trace::metrics 中的下标
+    declaration: Option<usize>,
+    value: Result<f32, String>,
+    charging: bool,
+}
+
+// 读取后的轨迹
+struct Trace {
+    // 按出现顺序排列的全部指标说明，同一指标可以有多条
+    metrics: Vec<TraceMetric>,
+    // 按时间排序的记录
+    records: Vec<TraceRecord>,
+}
+
+impl Trace {
+    fn metric(&self, record: &TraceRecord) -> Option<&TraceMetric> {
+        self.metrics.get(record.declaration?)
+    }
+}
+
+// 读取并校验轨迹文件
+fn load_trace(path: &str) -> Result<Trace, String> {
+    if path.trim().is_empty() {
+        return Err("未设置轨迹文件".to_string());
+    }
+    let content = std::fs::read_to_string(path).map_err(|e| format!("无法读取轨迹文件: {}", e))?;
+    let mut lines = content.lines().enumerate();
+
+    let header = lines.next().map(|(_, line)| line).unwrap_or("");
+    let mut header = header.split_whitespace();
+    if header.next() != Some(MAGIC) {
+        return Err("不是 RunCat 轨迹文件".to_string());
+    }
+    let version = match header.next().and_then(|v| v.parse::<u32>().ok()) {
+        Some(version @ (1 | VERSION)) => version,
+        Some(version) => return Err(format!("不支持的轨迹版本 {}", version)),
+        None => return Err("轨迹文件缺少版本号".to_string()),
+    };
+
+    let mut metrics = Vec::new();
+    // 各指标当前有效的说明
+    let mut declarations = HashMap::new();
+    // 当前驱动动画的指标
+    let mut driving: Option<String> = None;
+    let mut records = Vec::new();
+    for (index, line) in lines {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = || format!("轨迹文件第 {} 行格式无效", index + 1);
+        let mut fields = line.splitn(6, ' ');
+        let kind = fields.next().unwrap_or("");
+        let mut field = || fields.next().ok_or_else(invalid);
+
+        match kind {
+            "m" => {
+                let id = field()?.to_string();
+                let min = field()?.parse::<f32>().map_err(|_| invalid())?;
+                let max = field()?.parse::<f32>().map_err(|_| invalid())?;
+                let unit = match field()? {
+                    "-" => String::new(),
+                    unit => unit.to_string(),
+                };
+                let name = field().map(str::to_string).unwrap_or_else(|_| id.clone());
+                declarations.insert(id, metrics.len());
+                metrics.push(TraceMetric { name, unit, range: (min, max) });
+            }
+            "d" => driving = Some(field()?.to_string()),
+            "s" | "e" => {
+                let ms = field()?.parse::<u64>().map_err(|_| invalid())?;
+                let metric = field()?.to_string();
+                let rest: Vec<&str> = fields.collect();
+                let rest = rest.join(" ");
+                let (value, charging) = if kind == "s" {
+                    let mut parts = rest.split_whitespace();
+                    let value = parts.next().and_then(|v| v.parse::<f32>().ok()).ok_or_else(invalid)?;
+                    (Ok(value), parts.next() == Some("charging"))
+                } else {
+                    (Err(rest), false)
+                };
+                // 附加显示的指标不参与回放
+                if version != 1 && driving.as_deref() != Some(metric.as_str()) {
+                    continue;
+                }
+                let declaration = declarations.get(&metric).copied();
+                records.push(TraceRecord { ms, metric, declaration, value, charging });
+            }
+            _ => return Err(invalid()),
+        }
+    }
+
+    if records.is_empty() {
+        return Err("轨迹文件中没有样本".to_string());
+    }
+    records.sort_by_key(|r| r.ms);
+    Ok(Trace { metrics, records })
+}
+
+// 回放指标源：按录制时的时间间隔重放轨迹，可加速，播放完毕后从头循环
+pub struct ReplaySource {
+    trace: Result<Trace, String>,
+    speed: f32,
+    started: Instant,
+    // 当前播放到的记录
+    current: Option<usize>,
+    name: String,
+}
+
+impl ReplaySource {
+    pub fn new(path: &str, speed: f32) -> Self {
+        ReplaySource {
+            trace: load_trace(path),
+            speed: if speed.is_finite() && speed > 0.0 { speed } else { 1.0 },
+            started: Instant::now(),
+            current: None,
+            name: "回放".to_string(),
+        }
+    }
+
+    // 按设置创建
+    pub fn from_settings() -> Self {
+        ReplaySource::new(&crate::utils::get_replay_path(), crate::utils::get_replay_speed())
+    }
+
+    fn current_metric(&self) -> Option<&TraceMetric> {
+        let trace = self.trace.as_ref().ok()?;
+        trace.metric(&trace.records[self.current?])
+    }
+}
+
+impl MetricSource for ReplaySource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn unit(&self) -> &str {
+        self.current_metric().map(|m| m.unit.as_str()).unwrap_or("")
+    }
+
+    fn range(&self) -> (f32, f32) {
+        self.current_metric().map(|m| m.range).unwrap_or((0.0, 100.0))
+    }
+
+    fn sample(&mut self) -> Result<f32, String> {
+        let trace = self.trace.as_ref().map_err(|e| e.clone())?;
+        let records = &trace.records;
+
+        // 最后一条记录之后从头循环
+        let duration = records[records.len() - 1].ms.max(1);
+        let elapsed = (self.started.elapsed().as_secs_f64() * self.speed as f64 * 1000.0) as u64 % (duration + 1);
+        let index = records.partition_point(|r| r.ms <= elapsed).saturating_sub(1);
+        self.current = Some(index);
+
+        let record = &records[index];
+        let metric_name = trace.metric(record).map(|m| m.name.as_str()).unwrap_or(&record.metric);
+        self.name = format!("回放 {}", metric_name);
+        record.value.clone()
+    }
+
+    fn detail(&self) -> Option<String> {
+        let records = &self.trace.as_ref().ok()?.records;
+        let record = &records[self.current?];
+        let total = records[records.len() - 1].ms;
+        Some(format!(
+            "{:.1}/{:.1} 秒，{} 倍速",
+            record.ms as f32 / 1000.0,
+            total as f32 / 1000.0,
+            self.speed
+        ))
+    }
+
+    fn state(&self) -> MetricState {
+        match (&self.trace, self.current) {
+            (Ok(trace), Some(index)) if trace.records[index].charging => MetricState::Charging,
+            _ => MetricState::Normal,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    // 按测试设定返回数值的指标源
+    struct Scripted {
+        name: String,
+        range: (f32, f32),
+        charging: bool,
+    }
+
+    impl MetricSource for Scripted {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn unit(&self) -> &str {
+            "%"
+        }
+
+        fn range(&self) -> (f32, f32) {
+            self.range
+        }
+
+        fn sample(&mut self) -> Result<f32, String> {
+            Ok(0.0)
+        }
+
+        fn state(&self) -> MetricState {
+            if self.charging { MetricState::Charging } else { MetricState::Normal }
+        }
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("runcat-{}-{}.trace", name, std::process::id()))
+    }
+
+    // 让 ReplaySource 看起来已经播放了 ms 毫秒（实际时间）
+    fn seek(replay: &mut ReplaySource, ms: u64) -> Result<f32, String> {
+        replay.started = Instant::now() - Duration::from_millis(ms);
+        replay.sample()
+    }
+
+    #[test]
+    fn round_trip_with_speed() {
+        let path = temp_path("round-trip");
+        let mut source = Scripted { name: "CPU 占用".to_string(), range: (0.0, 100.0), charging: false };
+        let mut recorder = TraceRecorder::create(&path).unwrap();
+        let started = Instant::now();
+        let mut record = |ms: u64, source: &Scripted, result: Result<f32, String>| {
+            recorder.started = started - Duration::from_millis(ms);
+            recorder.record("cpu", source, &result, true).unwrap();
+        };
+
+        record(0, &source, Ok(10.0));
+        record(1000, &source, Err("无法读取\nCPU".to_string()));
+        source.charging = true;
+        record(2000, &source, Ok(30.5));
+        // 设置变化后名称和范围改变
+        source.charging = false;
+        source.name = "CPU (核心 0)".to_string();
+        source.range = (0.0, 50.0);
+        record(3000, &source, Ok(40.0));
+        record(4000, &source, Ok(45.0));
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(text.lines().filter(|line| line.starts_with("m ")).count(), 2);
+
+        // 4 倍速：实际 250 毫秒对应轨迹中的 1 秒
+        let mut replay = ReplaySource::new(path.to_str().unwrap(), 4.0);
+        assert_eq!(seek(&mut replay, 0), Ok(10.0));
+        assert_eq!(replay.name(), "回放 CPU 占用");
+        assert_eq!(replay.range(), (0.0, 100.0));
+        assert_eq!(seek(&mut replay, 300), Err("无法读取 CPU".to_string()));
+        assert_eq!(seek(&mut replay, 550), Ok(30.5));
+        assert!(replay.state() == MetricState::Charging);
+        assert_eq!(replay.detail().unwrap(), "2.0/4.0 秒，4 倍速");
+        assert_eq!(seek(&mut replay, 800), Ok(40.0));
+        assert!(replay.state() == MetricState::Normal);
+        assert_eq!(replay.name(), "回放 CPU (核心 0)");
+        assert_eq!(replay.range(), (0.0, 50.0));
+        assert_eq!(replay.unit(), "%");
+        // 播放完毕后从头循环
+        assert_eq!(seek(&mut replay, 1050), Ok(10.0));
+        assert_eq!(replay.range(), (0.0, 100.0));
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn rejects_unknown_versions() {
+        let path = temp_path("version");
+        std::fs::write(&path, "runcat-trace 3 0\nd cpu\ns 0 cpu 1\n").unwrap();
+        assert_eq!(load_trace(path.to_str().unwrap()).err().unwrap(), "不支持的轨迹版本 3");
+        std::fs::write(&path, "runcat-trace 2 0\nd cpu\ns x cpu 1\n").unwrap();
+        assert_eq!(load_trace(path.to_str().unwrap()).err().unwrap(), "轨迹文件第 3 行格式无效");
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn replays_only_the_driving_metric() {
+        let path = temp_path("extras");
+        let cpu = Scripted { name: "CPU 占用".to_string(), range: (0.0, 100.0), charging: false };
+        let memory = Scripted { name: "内存占用".to_string(), range: (0.0, 100.0), charging: false };
+        let mut recorder = TraceRecorder::create(&path).unwrap();
+        let started = Instant::now();
+        let mut record = |ms: u64, id: &str, source: &Scripted, value: f32, driving: bool| {
+            recorder.started = started - Duration::from_millis(ms);
+            recorder.record(id, source, &Ok(value), driving).unwrap();
+        };
+
+        record(0, "cpu", &cpu, 10.0, true);
+        record(0, "memory", &memory, 70.0, false);
+        record(1000, "cpu", &cpu, 20.0, true);
+        record(1000, "memory", &memory, 75.0, false);
+        // 切换驱动动画的指标
+        record(2000, "memory", &memory, 80.0, true);
+        record(2000, "cpu", &cpu, 30.0, false);
+        record(3000, "memory", &memory, 85.0, true);
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(text.lines().filter(|line| line.starts_with("s ")).count(), 7);
+        assert_eq!(text.lines().filter(|line| line.starts_with("d ")).collect::<Vec<_>>(), ["d cpu", "d memory"]);
+
+        let trace = load_trace(path.to_str().unwrap()).unwrap();
+        let values: Vec<_> = trace.records.iter().map(|r| (r.metric.as_str(), r.value.clone())).collect();
+        assert_eq!(values, [("cpu", Ok(10.0)), ("cpu", Ok(20.0)), ("memory", Ok(80.0)), ("memory", Ok(85.0))]);
+
+        let mut replay = ReplaySource::new(path.to_str().unwrap(), 1.0);
+        assert_eq!(seek(&mut replay, 2100), Ok(80.0));
+        assert_eq!(replay.name(), "回放 内存占用");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn reads_version_one() {
+        let path = temp_path("version-one");
+        std::fs::write(&path, "runcat-trace 1 0\nm cpu 0 100 % CPU\ns 0 cpu 1\ns 1000 cpu 2\n").unwrap();
+        let trace = load_trace(path.to_str().unwrap()).unwrap();
+        assert_eq!(trace.records.len(), 2);
+        assert_eq!(trace.metric(&trace.records[1]).map(|m| m.name.as_str()), Some("CPU"));
+        let _ = std::fs::remove_file(&path);
+    }
+}
\ No newline at end of file
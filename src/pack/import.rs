@@ -0,0 +1,746 @@
+// 跑者包导入：把动画 GIF、APNG 或精灵图转换为跑者包
+//
+// 导入过程只读写本地文件，不依赖 Windows API：解码并合成各帧，裁掉所有帧共同的透明边距，
+// 居中放入正方形后缩放到托盘常用的各个尺寸，每帧写成一个包含全部尺寸的 ICO 文件，
+// 再按各帧的显示时长写出清单
+
+use std::path::{Path, PathBuf};
+use serde_json::json;
+
+use super::{expand_to_rgba, packs_dir, Rgba, MANIFEST_NAME, FORMAT_VERSION};
+
+// 写入 ICO 的尺寸，覆盖 100%-300% 缩放下的托盘图标
+pub const ICON_SIZES: &[u32] = &[16, 20, 24, 32, 48];
+
+// 帧延迟为 0 或过短时使用的显示时长（毫秒），与浏览器的处理方式一致
+const DEFAULT_DELAY_MS: u32 = 100;
+
+// 动画画布和精灵图每帧的最大边长（像素）
+const MAX_SIDE: u32 = 4096;
+// 解码后全部帧（或整张精灵图）的最大字节数，避免异常文件耗尽内存
+const MAX_DECODED_BYTES: usize = 512 << 20;
+
+// 精灵图中各帧的排列方向
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SpriteLayout {
+    Horizontal,
+    Vertical,
+}
+
+pub struct ImportOptions {
+    pub input: PathBuf,
+    // 输出目录，为 None 时写入跑者包目录下以名称命名的子目录
+    pub output: Option<PathBuf>,
+    // 跑者名称，为 None 时使用输入文件名
+    pub name: Option<String>,
+    // 按精灵图导入；为 None 时 PNG 中没有动画则按宽高比自动判断
+    pub sprite: Option<SpriteLayout>,
+    // 精灵图的帧数，为 None 时按正方形帧计算
+    pub frames: Option<usize>,
+    // 精灵图每帧的显示时长（毫秒）
+    pub frame_ms: u32,
+    // 保留原图的透明边距
+    pub keep_margin: bool,
+    // 覆盖已存在的输出目录
+    pub force: bool,
+}
+
+// 解码后的一帧：与动画画布同尺寸的 RGBA 像素和显示时长
+struct Frame {
+    pixels: Vec<u8>,
+    delay_ms: u32,
+}
+
+struct Animation {
+    width: u32,
+    height: u32,
+    frames: Vec<Frame>,
+}
+
+// 导入并写出跑者包，返回跑者包目录
+pub fn import_pack(options: &ImportOptions) -> Result<PathBuf, String> {
+    let input = &options.input;
+    let bytes = std::fs::read(input).map_err(|e| format!("无法读取 {}: {}", input.display(), e))?;
+    let animation = decode(&bytes, options)?;
+
+    let name = match &options.name {
+        Some(name) if !name.trim().is_empty() => name.trim().to_string(),
+        _ => input
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .ok_or_else(|| format!("无法从 {} 得到跑者名称，请指定 --name", input.display()))?,
+    };
+    let output = match &options.output {
+        Some(output) => output.clone(),
+        None => packs_dir().join(file_name_for(&name)),
+    };
+    prepare_output(&output, options.force)?;
+
+    let (x, y, width, height) = if options.keep_margin {
+        (0, 0, animation.width, animation.height)
+    } else {
+        opaque_bounds(&animation).ok_or("所有帧都是完全透明的")?
+    };
+
+    let frames_dir = output.join("frames");
+    std::fs::create_dir_all(&frames_dir).map_err(|e| format!("无法创建 {}: {}", frames_dir.display(), e))?;
+
+    let mut files = Vec::new();
+    for (i, frame) in animation.frames.iter().enumerate() {
+        let cropped = crop(&frame.pixels, animation.width, x, y, width, height);
+        let square = pad_to_square(&cropped, width, height);
+        let images = ICON_SIZES
+            .iter()
+            .map(|&size| resize(&square, size))
+            .collect::<Vec<Rgba>>();
+        let ico = encode_ico(&images)?;
+
+        let file = format!("frames/{}.ico", i);
+        std::fs::write(output.join(&file), ico).map_err(|e| format!("无法写入 {}: {}", file, e))?;
+        files.push(file);
+    }
+
+    let weights: Vec<u32> = animation.frames.iter().map(|frame| frame.delay_ms).collect();
+    let manifest = json!({
+        "format": FORMAT_VERSION,
+        "name": name,
+        "light": files,
+        "weights": weights,
+    });
+    let text = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+    std::fs::write(output.join(MANIFEST_NAME), text + "\n").map_err(|e| format!("无法写入 {}: {}", MANIFEST_NAME, e))?;
+
+    Ok(output)
+}
+
+// 按文件内容选择解码方式
+fn decode(bytes: &[u8], options: &ImportOptions) -> Result<Animation, String> {
+    let animation = if bytes.starts_with(b"GIF8") {
+        if options.sprite.is_some() {
+            return Err("精灵图只支持 PNG".to_string());
+        }
+        decode_gif(bytes)?
+    } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        decode_png_animation(bytes, options)?
+    } else {
+        return Err("只支持 GIF、APNG 和 PNG 精灵图".to_string());
+    };
+
+    if animation.frames.is_empty() {
+        return Err("没有可导入的帧".to_string());
+    }
+    Ok(animation)
+}
+
+fn decode_gif(bytes: &[u8]) -> Result<Animation, String> {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(bytes).map_err(|e| format!("GIF 解码失败: {}", e))?;
+    let (width, height) = (decoder.width() as u32, decoder.height() as u32);
+
+    let mut canvas = vec![0u8; canvas_len(width, height)?];
+    let mut frames = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().map_err(|e| format!("GIF 解码失败: {}", e))? {
+        let region = (frame.left as u32, frame.top as u32, frame.width as u32, frame.height as u32);
+        let previous = (frame.dispose == gif::DisposalMethod::Previous).then(|| canvas.clone());
+
+        composite(&mut canvas, width, height, &frame.buffer, region, true);
+        check_budget(frames.len() + 1, canvas.len())?;
+        frames.push(Frame {
+            pixels: canvas.clone(),
+            delay_ms: delay_or_default(frame.delay as u32 * 10),
+        });
+
+        match frame.dispose {
+            gif::DisposalMethod::Background => clear(&mut canvas, width, height, region),
+            gif::DisposalMethod::Previous => canvas = previous.unwrap_or(canvas),
+            _ => {}
+        }
+    }
+    Ok(Animation { width, height, frames })
+}
+
+// PNG：带动画控制块的按 APNG 合成各帧，否则按精灵图切分
+fn decode_png_animation(bytes: &[u8], options: &ImportOptions) -> Result<Animation, String> {
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::normalize_to_color8() | png::Transformations::ALPHA);
+    let mut reader = decoder.read_info().map_err(|e| format!("PNG 解码失败: {}", e))?;
+    let (width, height) = (reader.info().width, reader.info().height);
+    let animated = reader
+        .info()
+        .animation_control
+        .filter(|control| control.num_frames > 1);
+
+    let animation_frames = match animated {
+        Some(control) if options.sprite.is_none() => control.num_frames,
+        _ => {
+            pixel_bytes(width, height)
+                .filter(|&len| len <= MAX_DECODED_BYTES)
+                .ok_or_else(|| format!("精灵图 {}x{} 过大", width, height))?;
+            let mut buf = vec![0; reader.output_buffer_size()];
+            let info = reader.next_frame(&mut buf).map_err(|e| format!("PNG 解码失败: {}", e))?;
+            buf.truncate(info.buffer_size());
+            let image = Rgba { width, height, pixels: expand_to_rgba(buf, info.color_type)? };
+            return split_sprite_sheet(&image, options);
+        }
+    };
+
+    let mut canvas = vec![0u8; canvas_len(width, height)?];
+    let mut frames = Vec::new();
+    let mut buf = vec![0; reader.output_buffer_size()];
+    while frames.len() < animation_frames as usize {
+        let info = reader.next_frame(&mut buf).map_err(|e| format!("APNG 解码失败: {}", e))?;
+        // 没有帧控制块的默认图像不属于动画
+        let control = match reader.info().frame_control {
+            Some(control) => control,
+            None => continue,
+        };
+        let pixels = expand_to_rgba(buf[..info.buffer_size()].to_vec(), info.color_type)?;
+        let region = (control.x_offset, control.y_offset, control.width, control.height);
+        let previous = (control.dispose_op == png::DisposeOp::Previous).then(|| canvas.clone());
+
+        if control.blend_op == png::BlendOp::Source {
+            clear(&mut canvas, width, height, region);
+        }
+        composite(&mut canvas, width, height, &pixels, region, control.blend_op == png::BlendOp::Over);
+        check_budget(frames.len() + 1, canvas.len())?;
+        let den = if control.delay_den == 0 { 100 } else { control.delay_den as u32 };
+        frames.push(Frame {
+            pixels: canvas.clone(),
+            delay_ms: delay_or_default(control.delay_num as u32 * 1000 / den),
+        });
+
+        match control.dispose_op {
+            png::DisposeOp::Background => clear(&mut canvas, width, height, region),
+            png::DisposeOp::Previous => canvas = previous.unwrap_or(canvas),
+            _ => {}
+        }
+    }
+    Ok(Animation { width, height, frames })
+}
+
+// 切分精灵图；未指定排列方向时按宽高比判断，未指定帧数时按正方形帧计算
+fn split_sprite_sheet(image: &Rgba, options: &ImportOptions) -> Result<Animation, String> {
+    let (w, h) = (image.width, image.height);
+    let layout = match options.sprite {
+        Some(layout) => layout,
+        None if w > h && w % h == 0 => SpriteLayout::Horizontal,
+        None if h > w && h % w == 0 => SpriteLayout::Vertical,
+        None => return Err("PNG 中没有动画，也无法按宽高比判断精灵图的排列方式，请指定 --sprite 和 --frames".to_string()),
+    };
+    let (length, side) = match layout {
+        SpriteLayout::Horizontal => (w, h),
+        SpriteLayout::Vertical => (h, w),
+    };
+    let count = options.frames.unwrap_or((length / side.max(1)) as usize);
+    if count == 0 || length % count as u32 != 0 {
+        return Err(format!("精灵图长 {} 像素，无法平均分成 {} 帧", length, count));
+    }
+
+    let step = length / count as u32;
+    let (frame_w, frame_h) = match layout {
+        SpriteLayout::Horizontal => (step, h),
+        SpriteLayout::Vertical => (w, step),
+    };
+    canvas_len(frame_w, frame_h)?;
+    let frames = (0..count as u32)
+        .map(|i| {
+            let (x, y) = match layout {
+                SpriteLayout::Horizontal => (i * step, 0),
+                SpriteLayout::Vertical => (0, i * step),
+            };
+            Frame {
+                pixels: crop(&image.pixels, w, x, y, frame_w, frame_h),
+                delay_ms: options.frame_ms.max(1),
+            }
+        })
+        .collect();
+    Ok(Animation { width: frame_w, height: frame_h, frames })
+}
+
+// RGBA 图像的字节数，在 usize 中计算，溢出时返回 None
+fn pixel_bytes(width: u32, height: u32) -> Option<usize> {
+    (width as usize).checked_mul(height as usize)?.checked_mul(4)
+}
+
+// 检查画布尺寸并返回其字节数
+fn canvas_len(width: u32, height: u32) -> Result<usize, String> {
+    if width == 0 || height == 0 || width > MAX_SIDE || height > MAX_SIDE {
+        return Err(format!("图像尺寸 {}x{} 无效，每边最多 {} 像素", width, height, MAX_SIDE));
+    }
+    pixel_bytes(width, height).ok_or_else(|| format!("图像尺寸 {}x{} 过大", width, height))
+}
+
+// 检查解码出 count 帧后是否超过内存上限
+fn check_budget(count: usize, frame_len: usize) -> Result<(), String> {
+    match count.checked_mul(frame_len) {
+        Some(total) if total <= MAX_DECODED_BYTES => Ok(()),
+        _ => Err(format!("动画解码后超过 {} MB，请减少帧数或尺寸", MAX_DECODED_BYTES >> 20)),
+    }
+}
+
+fn delay_or_default(delay_ms: u32) -> u32 {
+    if delay_ms < 20 {
+        DEFAULT_DELAY_MS
+    } else {
+        delay_ms
+    }
+}
+
+// 把一帧的像素合成到画布的指定区域；blend 为 false 时直接覆盖
+fn composite(canvas: &mut [u8], width: u32, height: u32, pixels: &[u8], region: (u32, u32, u32, u32), blend: bool) {
+    let (width, height) = (width as usize, height as usize);
+    let (left, top, w, h) = (region.0 as usize, region.1 as usize, region.2 as usize, region.3 as usize);
+    for row in 0..h {
+        let y = top.saturating_add(row);
+        if y >= height {
+            break;
+        }
+        for col in 0..w {
+            let x = left.saturating_add(col);
+            if x >= width {
+                break;
+            }
+            let src_index = (row * w + col) * 4;
+            let Some(src) = pixels.get(src_index..src_index + 4) else {
+                return;
+            };
+            let dst_index = (y * width + x) * 4;
+            let dst = &mut canvas[dst_index..dst_index + 4];
+            if blend {
+                over(dst, src);
+            } else {
+                dst.copy_from_slice(src);
+            }
+        }
+    }
+}
+
+// 源像素按透明度叠加在目标像素上（非预乘 alpha）
+fn over(dst: &mut [u8], src: &[u8]) {
+    let sa = src[3] as f32 / 255.0;
+    if sa >= 1.0 {
+        dst.copy_from_slice(src);
+        return;
+    }
+    let da = dst[3] as f32 / 255.0;
+    let out_a = sa + da * (1.0 - sa);
+    if out_a <= 0.0 {
+        dst.copy_from_slice(&[0, 0, 0, 0]);
+        return;
+    }
+    for c in 0..3 {
+        let value = (src[c] as f32 * sa + dst[c] as f32 * da * (1.0 - sa)) / out_a;
+        dst[c] = value.round() as u8;
+    }
+    dst[3] = (out_a * 255.0).round() as u8;
+}
+
+// 把画布的指定区域清为透明
+fn clear(canvas: &mut [u8], width: u32, height: u32, region: (u32, u32, u32, u32)) {
+    let (width, height) = (width as usize, height as usize);
+    let (left, top, w, h) = (region.0 as usize, region.1 as usize, region.2 as usize, region.3 as usize);
+    for y in top..top.saturating_add(h).min(height) {
+        let start = (y * width + left.min(width)) * 4;
+        let end = (y * width + left.saturating_add(w).min(width)) * 4;
+        canvas[start..end].fill(0);
+    }
+}
+
+// 所有帧中不透明像素的共同外接矩形 (x, y, 宽, 高)，全部透明时返回 None
+fn opaque_bounds(animation: &Animation) -> Option<(u32, u32, u32, u32)> {
+    let width = animation.width as usize;
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (u32::MAX, u32::MAX, 0, 0);
+    for frame in &animation.frames {
+        for (i, pixel) in frame.pixels.chunks_exact(4).enumerate() {
+            if pixel[3] == 0 {
+                continue;
+            }
+            let (x, y) = ((i % width) as u32, (i / width) as u32);
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+    }
+    (min_x <= max_x).then(|| (min_x, min_y, max_x - min_x + 1, max_y - min_y + 1))
+}
+
+fn crop(pixels: &[u8], stride: u32, x: u32, y: u32, width: u32, height: u32) -> Vec<u8> {
+    let (stride, x, y, width, height) = (stride as usize, x as usize, y as usize, width as usize, height as usize);
+    let mut out = Vec::with_capacity(width * height * 4);
+    for row in y..y + height {
+        let start = (row * stride + x) * 4;
+        out.extend_from_slice(&pixels[start..start + width * 4]);
+    }
+    out
+}
+
+// 居中放入透明的正方形画布
+fn pad_to_square(pixels: &[u8], width: u32, height: u32) -> Rgba {
+    let side = width.max(height);
+    let (w, h, n) = (width as usize, height as usize, side as usize);
+    let (offset_x, offset_y) = ((n - w) / 2, (n - h) / 2);
+    let mut out = vec![0u8; n * n * 4];
+    for row in 0..h {
+        let src = row * w * 4;
+        let dst = ((row + offset_y) * n + offset_x) * 4;
+        out[dst..dst + w * 4].copy_from_slice(&pixels[src..src + w * 4]);
+    }
+    Rgba { width: side, height: side, pixels: out }
+}
+
+// 按面积平均缩放正方形图像；颜色按透明度加权，避免透明像素的颜色渗到边缘
+fn resize(image: &Rgba, size: u32) -> Rgba {
+    let scale = image.width as f32 / size as f32;
+    let mut pixels = Vec::with_capacity(size as usize * size as usize * 4);
+
+    for dy in 0..size {
+        let (y0, y1) = (dy as f32 * scale, (dy + 1) as f32 * scale);
+        for dx in 0..size {
+            let (x0, x1) = (dx as f32 * scale, (dx + 1) as f32 * scale);
+            let mut sum = [0f32; 4];
+            let mut area = 0f32;
+
+            for sy in y0.floor() as u32..(y1.ceil() as u32).min(image.height) {
+                let cover_y = (y1.min(sy as f32 + 1.0) - y0.max(sy as f32)).max(0.0);
+                for sx in x0.floor() as u32..(x1.ceil() as u32).min(image.width) {
+                    let cover = cover_y * (x1.min(sx as f32 + 1.0) - x0.max(sx as f32)).max(0.0);
+                    let i = (sy as usize * image.width as usize + sx as usize) * 4;
+                    let alpha = image.pixels[i + 3] as f32 * cover;
+                    for (c, total) in sum.iter_mut().take(3).enumerate() {
+                        *total += image.pixels[i + c] as f32 * alpha;
+                    }
+                    sum[3] += alpha;
+                    area += cover;
+                }
+            }
+
+            if sum[3] > 0.0 {
+                pixels.extend_from_slice(&[
+                    (sum[0] / sum[3]).round() as u8,
+                    (sum[1] / sum[3]).round() as u8,
+                    (sum[2] / sum[3]).round() as u8,
+                    (sum[3] / area).round() as u8,
+                ]);
+            } else {
+                pixels.extend_from_slice(&[0, 0, 0, 0]);
+            }
+        }
+    }
+    Rgba { width: size, height: size, pixels }
+}
+
+fn encode_png(image: &Rgba) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, image.width, image.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| format!("PNG 编码失败: {}", e))?;
+    writer.write_image_data(&image.pixels).map_err(|e| format!("PNG 编码失败: {}", e))?;
+    writer.finish().map_err(|e| format!("PNG 编码失败: {}", e))?;
+    Ok(bytes)
+}
+
+// 写出 ICO 文件：文件头、每个尺寸一个目录项，图像数据以 PNG 格式存放（Windows Vista 起支持）
+pub fn encode_ico(images: &[Rgba]) -> Result<Vec<u8>, String> {
+    let encoded = images.iter().map(encode_png).collect::<Result<Vec<_>, _>>()?;
+
+    let mut ico = Vec::new();
+    ico.extend_from_slice(&0u16.to_le_bytes());
+    ico.extend_from_slice(&1u16.to_le_bytes());
+    ico.extend_from_slice(&(images.len() as u16).to_le_bytes());
+
+    let mut offset = 6 + 16 * images.len();
+    for (image, data) in images.iter().zip(&encoded) {
+        // 宽高为 256 时写作 0
+        ico.push(image.width.min(256) as u8);
+        ico.push(image.height.min(256) as u8);
+        ico.push(0); // 调色板颜色数
+        ico.push(0); // 保留
+        ico.extend_from_slice(&1u16.to_le_bytes()); // 平面数
+        ico.extend_from_slice(&32u16.to_le_bytes()); // 位深
+        ico.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        ico.extend_from_slice(&(offset as u32).to_le_bytes());
+        offset += data.len();
+    }
+    for data in &encoded {
+        ico.extend_from_slice(data);
+    }
+    Ok(ico)
+}
+
+// 名称中不能用作文件名的字符替换为 _
+fn file_name_for(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_control() || "<>:\"/\\|?*".contains(c) { '_' } else { c })
+        .collect::<String>()
+        .trim_end_matches(['.', ' '])
+        .to_string()
+}
+
+// 检查输出目录：不存在或为空时可直接写入；已是跑者包时需要 --force，并只删除导入时写出的帧文件。
+// 其他非空目录一律拒绝，避免误删用户的文件
+fn prepare_output(output: &Path, force: bool) -> Result<(), String> {
+    let has_content = std::fs::read_dir(output).map(|mut entries| entries.next().is_some()).unwrap_or(false);
+    if has_content {
+        if !output.join(MANIFEST_NAME).is_file() {
+            return Err(format!("{} 不是空目录，也不是跑者包，请指定其他输出目录", output.display()));
+        }
+        if !force {
+            return Err(format!("{} 已存在，使用 --force 覆盖", output.display()));
+        }
+        remove_imported_frames(&output.join("frames"))?;
+    }
+    std::fs::create_dir_all(output).map_err(|e| format!("无法创建 {}: {}", output.display(), e))
+}
+
+// 删除 frames 目录中导入时写出的 <序号>.ico，其他文件保留
+fn remove_imported_frames(frames_dir: &Path) -> Result<(), String> {
+    let Ok(entries) = std::fs::read_dir(frames_dir) else {
+        return Ok(());
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let imported = entry.file_type().is_ok_and(|t| t.is_file())
+            && path.extension().is_some_and(|ext| ext == "ico")
+            && path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .is_some_and(|stem| !stem.is_empty() && stem.bytes().all(|b| b.is_ascii_digit()));
+        if imported {
+            std::fs::remove_file(&path).map_err(|e| format!("无法删除 {}: {}", path.display(), e))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const GREEN: [u8; 4] = [0, 255, 0, 255];
+    const CLEAR: [u8; 4] = [0, 0, 0, 0];
+
+    fn options() -> ImportOptions {
+        ImportOptions {
+            input: PathBuf::new(),
+            output: None,
+            name: None,
+            sprite: None,
+            frames: None,
+            frame_ms: 100,
+            keep_margin: false,
+            force: false,
+        }
+    }
+
+    fn pixels(colors: &[[u8; 4]]) -> Vec<u8> {
+        colors.concat()
+    }
+
+    // 调色板：0 透明、1 红、2 绿
+    fn gif_bytes(width: u16, height: u16, frames: &[(u16, u16, Vec<u8>, gif::DisposalMethod)]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut encoder = gif::Encoder::new(&mut bytes, width, height, &[0, 0, 0, 255, 0, 0, 0, 255, 0]).unwrap();
+        for (left, width, buffer, dispose) in frames {
+            let frame = gif::Frame {
+                left: *left,
+                width: *width,
+                height: 1,
+                buffer: buffer.clone().into(),
+                transparent: Some(0),
+                delay: 5,
+                dispose: *dispose,
+                ..Default::default()
+            };
+            encoder.write_frame(&frame).unwrap();
+        }
+        drop(encoder);
+        bytes
+    }
+
+    // 两帧 2x1 的 APNG：第一帧全红，第二帧按 blend 合成
+    fn apng_bytes(second: &[u8], blend: png::BlendOp) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, 2, 1);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(2, 0).unwrap();
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&pixels(&[RED, RED])).unwrap();
+        writer.set_blend_op(blend).unwrap();
+        writer.write_image_data(second).unwrap();
+        writer.finish().unwrap();
+        bytes
+    }
+
+    #[test]
+    fn gif_background_disposal_clears_region() {
+        let bytes = gif_bytes(4, 1, &[
+            (0, 2, vec![1, 1], gif::DisposalMethod::Background),
+            (2, 2, vec![2, 2], gif::DisposalMethod::Keep),
+        ]);
+        let animation = decode_gif(&bytes).unwrap();
+        assert_eq!(animation.frames[0].pixels, pixels(&[RED, RED, CLEAR, CLEAR]));
+        assert_eq!(animation.frames[1].pixels, pixels(&[CLEAR, CLEAR, GREEN, GREEN]));
+        assert_eq!(animation.frames[1].delay_ms, 50);
+    }
+
+    #[test]
+    fn gif_previous_disposal_restores_canvas() {
+        let bytes = gif_bytes(4, 1, &[
+            (0, 4, vec![1, 1, 1, 1], gif::DisposalMethod::Keep),
+            (0, 2, vec![2, 2], gif::DisposalMethod::Previous),
+            (3, 1, vec![0], gif::DisposalMethod::Keep),
+        ]);
+        let animation = decode_gif(&bytes).unwrap();
+        assert_eq!(animation.frames[1].pixels, pixels(&[GREEN, GREEN, RED, RED]));
+        assert_eq!(animation.frames[2].pixels, pixels(&[RED, RED, RED, RED]));
+    }
+
+    #[test]
+    fn apng_source_replaces_pixels() {
+        let second = pixels(&[[0, 0, 255, 128], CLEAR]);
+        let animation = decode_png_animation(&apng_bytes(&second, png::BlendOp::Source), &options()).unwrap();
+        assert_eq!(animation.frames.len(), 2);
+        assert_eq!(animation.frames[0].pixels, pixels(&[RED, RED]));
+        assert_eq!(animation.frames[1].pixels, second);
+    }
+
+    #[test]
+    fn apng_over_blends_pixels() {
+        let second = pixels(&[[0, 0, 255, 128], CLEAR]);
+        let animation = decode_png_animation(&apng_bytes(&second, png::BlendOp::Over), &options()).unwrap();
+        assert_eq!(animation.frames[1].pixels, pixels(&[[127, 0, 128, 255], RED]));
+    }
+
+    #[test]
+    fn splits_sprite_sheets() {
+        // 每帧 2x2，像素颜色的红色分量为帧序号
+        let frame = |i: u8| [i, 0, 0, 255];
+        let horizontal = Rgba {
+            width: 6,
+            height: 2,
+            pixels: pixels(&[0, 0, 1, 1, 2, 2, 0, 0, 1, 1, 2, 2].map(frame)),
+        };
+        let animation = split_sprite_sheet(&horizontal, &options()).unwrap();
+        assert_eq!((animation.width, animation.height, animation.frames.len()), (2, 2, 3));
+        for (i, frame) in animation.frames.iter().enumerate() {
+            assert!(frame.pixels.chunks_exact(4).all(|p| p[0] == i as u8));
+        }
+
+        let vertical = Rgba { width: 2, height: 6, pixels: pixels(&[0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2].map(frame)) };
+        let animation = split_sprite_sheet(&vertical, &options()).unwrap();
+        assert_eq!((animation.width, animation.height, animation.frames.len()), (2, 2, 3));
+        assert!(animation.frames[2].pixels.chunks_exact(4).all(|p| p[0] == 2));
+
+        // 指定方向和帧数时帧可以不是正方形
+        let options = ImportOptions { sprite: Some(SpriteLayout::Vertical), frames: Some(2), ..options() };
+        let animation = split_sprite_sheet(&vertical, &options).unwrap();
+        assert_eq!((animation.width, animation.height, animation.frames.len()), (2, 3, 2));
+    }
+
+    #[test]
+    fn rejects_uneven_sprite_sheets() {
+        let sheet = Rgba { width: 6, height: 2, pixels: vec![255; 6 * 2 * 4] };
+        let four = ImportOptions { sprite: Some(SpriteLayout::Horizontal), frames: Some(4), ..options() };
+        assert!(split_sprite_sheet(&sheet, &four).is_err());
+        let zero = ImportOptions { frames: Some(0), ..four };
+        assert!(split_sprite_sheet(&sheet, &zero).is_err());
+
+        // 无法按宽高比判断方向
+        let sheet = Rgba { width: 3, height: 2, pixels: vec![255; 3 * 2 * 4] };
+        assert!(split_sprite_sheet(&sheet, &options()).is_err());
+    }
+
+    #[test]
+    fn opaque_bounds_of_transparent_frames() {
+        let mut animation = Animation {
+            width: 3,
+            height: 3,
+            frames: vec![Frame { pixels: vec![0; 36], delay_ms: 100 }, Frame { pixels: vec![0; 36], delay_ms: 100 }],
+        };
+        assert_eq!(opaque_bounds(&animation), None);
+
+        animation.frames[0].pixels[(3 + 1) * 4 + 3] = 255;
+        animation.frames[1].pixels[(2 * 3 + 2) * 4 + 3] = 1;
+        assert_eq!(opaque_bounds(&animation), Some((1, 1, 2, 2)));
+    }
+
+    #[test]
+    fn resize_weights_colors_by_alpha() {
+        // 透明像素的蓝色不应渗入结果
+        let image = Rgba { width: 2, height: 2, pixels: pixels(&[RED, [0, 0, 255, 0], [0, 0, 255, 0], [0, 0, 255, 0]]) };
+        assert_eq!(resize(&image, 1).pixels, [255, 0, 0, 64]);
+
+        let image = Rgba { width: 2, height: 2, pixels: pixels(&[RED, RED, GREEN, GREEN]) };
+        let resized = resize(&image, 4);
+        assert_eq!(resized.pixels[..4], RED);
+        assert_eq!(resized.pixels[resized.pixels.len() - 4..], GREEN);
+        assert_eq!(resize(&image, 1).pixels, [128, 128, 0, 255]);
+    }
+
+    #[test]
+    fn ico_round_trip() {
+        let images: Vec<Rgba> = [16u32, 32, 48]
+            .iter()
+            .map(|&size| Rgba { width: size, height: size, pixels: vec![size as u8; (size * size * 4) as usize] })
+            .collect();
+        let ico = encode_ico(&images).unwrap();
+        assert_eq!(ico[..6], [0, 0, 1, 0, 3, 0]);
+
+        for (wanted, expected) in [(16, 16), (20, 32), (32, 32), (64, 48)] {
+            let (width, data) = crate::pack::select_ico_image(&ico, wanted).unwrap();
+            assert_eq!(width, expected);
+            let image = crate::pack::decode_png(data).unwrap();
+            assert_eq!((image.width, image.height), (expected, expected));
+            assert!(image.pixels.iter().all(|&p| p == expected as u8));
+        }
+        assert!(crate::pack::select_ico_image(&ico[..ico.len() - 1], 16).is_err());
+    }
+
+    #[test]
+    fn rejects_oversized_images() {
+        assert_eq!(canvas_len(MAX_SIDE, MAX_SIDE), Ok(MAX_SIDE as usize * MAX_SIDE as usize * 4));
+        assert!(canvas_len(MAX_SIDE + 1, 1).is_err());
+        assert!(canvas_len(0, 1).is_err());
+        assert!(check_budget(1, canvas_len(MAX_SIDE, MAX_SIDE).unwrap()).is_ok());
+        assert!(check_budget(usize::MAX, 4).is_err());
+
+        let bytes = gif_bytes(u16::MAX, 1, &[(0, 1, vec![1], gif::DisposalMethod::Keep)]);
+        assert!(decode_gif(&bytes).err().is_some_and(|e| e.contains("4096")));
+    }
+
+    #[test]
+    fn prepare_output_protects_other_files() {
+        let dir = std::env::temp_dir().join(format!("run_cat_import_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        // 空目录和不存在的目录可直接写入
+        prepare_output(&dir, false).unwrap();
+        prepare_output(&dir, false).unwrap();
+
+        // 非空但不是跑者包的目录即使有 --force 也拒绝
+        std::fs::write(dir.join("notes.txt"), "keep").unwrap();
+        assert!(prepare_output(&dir, true).is_err());
+        assert!(dir.join("notes.txt").is_file());
+
+        // 已有的跑者包需要 --force，且只删除导入时写出的帧
+        std::fs::create_dir_all(dir.join("frames")).unwrap();
+        std::fs::write(dir.join(MANIFEST_NAME), "{}").unwrap();
+        for file in ["frames/0.ico", "frames/12.ico", "frames/keep.ico", "frames/3.png"] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+        assert!(prepare_output(&dir, false).is_err());
+        prepare_output(&dir, true).unwrap();
+        assert!(!dir.join("frames/0.ico").exists());
+        assert!(!dir.join("frames/12.ico").exists());
+        assert!(dir.join("frames/keep.ico").is_file());
+        assert!(dir.join("frames/3.png").is_file());
+        assert!(dir.join("notes.txt").is_file());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
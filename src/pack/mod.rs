@@ -0,0 +1,337 @@
+// 跑者包格式：清单文件 runner.json 加上浅色、深色两组帧图像，可以是目录或 zip 压缩包
+//
+// runner.json 示例：
+// {
+//   "format": 1,
+//   "name": "鹦鹉",
+//   "light": ["light/0.png", "light/1.png", "light/2.png", "light/3.png", "light/4.png"],
+//   "dark": ["dark/0.ico", "dark/1.ico", "dark/2.ico", "dark/3.ico", "dark/4.ico"],
+//   "weights": [1, 1, 2, 1, 1]
+// }
+// 帧数不限，dark 的帧数需与 light 相同。weights 为各帧的相对显示时长，可省略，省略时各帧时长相同。
+// 帧图像可为 PNG 或 ICO，按文件内容识别；dark 可省略，省略时深色模式也使用 light。
+// 路径相对于清单所在目录；zip 中的清单可以位于顶层或唯一的顶层目录下
+
+pub mod import;
+
+use std::fs::File;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use serde_json::Value;
+
+pub const MANIFEST_NAME: &str = "runner.json";
+// 清单格式版本，只在不兼容时增加
+pub const FORMAT_VERSION: u64 = 1;
+
+// 一帧图像的原始数据
+pub enum FrameImage {
+    Png(Vec<u8>),
+    Ico(Vec<u8>),
+}
+
+impl FrameImage {
+    fn from_bytes(name: &str, bytes: Vec<u8>) -> Result<FrameImage, String> {
+        if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Ok(FrameImage::Png(bytes))
+        } else if bytes.starts_with(&[0, 0, 1, 0]) {
+            Ok(FrameImage::Ico(bytes))
+        } else {
+            Err(format!("{} 不是 PNG 或 ICO 图像", name))
+        }
+    }
+}
+
+// 解码后的 RGBA 图像
+pub struct Rgba {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+// 把 PNG 解码为 8 位 RGBA
+pub fn decode_png(bytes: &[u8]) -> Result<Rgba, String> {
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::normalize_to_color8() | png::Transformations::ALPHA);
+    let mut reader = decoder.read_info().map_err(|e| format!("PNG 解码失败: {}", e))?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).map_err(|e| format!("PNG 解码失败: {}", e))?;
+    buf.truncate(info.buffer_size());
+    let pixels = expand_to_rgba(buf, info.color_type)?;
+    Ok(Rgba { width: info.width, height: info.height, pixels })
+}
+
+// 把经过 normalize_to_color8 和 ALPHA 变换的像素展开成 RGBA；灰度图变换后为灰度加透明度
+pub fn expand_to_rgba(buf: Vec<u8>, color_type: png::ColorType) -> Result<Vec<u8>, String> {
+    match color_type {
+        png::ColorType::Rgba => Ok(buf),
+        png::ColorType::GrayscaleAlpha => Ok(buf.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect()),
+        other => Err(format!("不支持的 PNG 颜色类型: {:?}", other)),
+    }
+}
+
+// 从 ICO 文件中选出最适合 wanted 像素的一项，返回其宽度和图像数据（PNG 或 BMP）
+pub fn select_ico_image(bytes: &[u8], wanted: u32) -> Result<(u32, &[u8]), String> {
+    let u16_at = |offset: usize| bytes.get(offset..offset + 2).map(|b| u16::from_le_bytes([b[0], b[1]]));
+    let u32_at = |offset: usize| bytes.get(offset..offset + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+
+    let count = u16_at(4).ok_or("ICO 文件不完整")? as usize;
+
+    // 目录项：宽、高（0 表示 256）、颜色数、保留、平面数、位深、数据长度、数据偏移
+    let mut best: Option<(u32, usize, usize)> = None;
+    for i in 0..count {
+        let entry = 6 + i * 16;
+        let width = match *bytes.get(entry).ok_or("ICO 文件不完整")? {
+            0 => 256,
+            w => w as u32,
+        };
+        let size = u32_at(entry + 8).ok_or("ICO 文件不完整")? as usize;
+        let offset = u32_at(entry + 12).ok_or("ICO 文件不完整")? as usize;
+        if offset.checked_add(size).is_none_or(|end| end > bytes.len()) {
+            return Err("ICO 文件不完整".to_string());
+        }
+        // 优先选择不小于所需尺寸的最小一项，都比所需尺寸小时选择最大的一项
+        let better = match best {
+            None => true,
+            Some((best_width, _, _)) if best_width < wanted => width > best_width,
+            Some((best_width, _, _)) => width >= wanted && width < best_width,
+        };
+        if better {
+            best = Some((width, offset, size));
+        }
+    }
+
+    let (width, offset, size) = best.ok_or("ICO 文件中没有图像")?;
+    Ok((width, &bytes[offset..offset + size]))
+}
+
+// 已读取的跑者包
+pub struct RunnerPack {
+    pub name: String,
+    pub light: Vec<FrameImage>,
+    pub dark: Vec<FrameImage>,
+    // 各帧的相对显示时长，与帧数相同
+    pub weights: Vec<f32>,
+}
+
+// 读取目录或 zip 形式的跑者包
+pub fn load_pack(path: &Path) -> Result<RunnerPack, String> {
+    if path.is_dir() {
+        load_from(|name| {
+            let file = safe_join(path, name)?;
+            std::fs::read(&file).map_err(|e| format!("无法读取 {}: {}", name, e))
+        })
+    } else {
+        let mut archive = open_zip(path)?;
+        let prefix = zip_manifest_prefix(&mut archive)?;
+        load_from(|name| {
+            safe_join(Path::new(""), name)?;
+            let entry_name = format!("{}{}", prefix, name.replace('\\', "/"));
+            let mut entry = archive
+                .by_name(&entry_name)
+                .map_err(|_| format!("压缩包中没有 {}", name))?;
+            let mut bytes = Vec::new();
+            entry.read_to_end(&mut bytes).map_err(|e| format!("无法读取 {}: {}", name, e))?;
+            Ok(bytes)
+        })
+    }
+}
+
+// 只读取清单中的名称，用于菜单列表
+pub fn read_pack_name(path: &Path) -> Result<String, String> {
+    let manifest = if path.is_dir() {
+        std::fs::read(path.join(MANIFEST_NAME)).map_err(|e| e.to_string())?
+    } else {
+        let mut archive = open_zip(path)?;
+        let prefix = zip_manifest_prefix(&mut archive)?;
+        let mut entry = archive
+            .by_name(&format!("{}{}", prefix, MANIFEST_NAME))
+            .map_err(|e| e.to_string())?;
+        let mut bytes = Vec::new();
+        entry.read_to_end(&mut bytes).map_err(|e| e.to_string())?;
+        bytes
+    };
+    parse_manifest(&manifest).map(|m| m.name)
+}
+
+struct Manifest {
+    name: String,
+    light: Vec<String>,
+    dark: Option<Vec<String>>,
+    weights: Vec<f32>,
+}
+
+fn parse_manifest(bytes: &[u8]) -> Result<Manifest, String> {
+    let root: Value = serde_json::from_slice(bytes).map_err(|e| format!("{} 格式无效: {}", MANIFEST_NAME, e))?;
+
+    match root.get("format").and_then(Value::as_u64) {
+        Some(FORMAT_VERSION) => {}
+        Some(version) => return Err(format!("不支持的跑者包格式版本 {}", version)),
+        None => return Err(format!("{} 缺少 format 字段", MANIFEST_NAME)),
+    }
+
+    let name = root
+        .get("name")
+        .and_then(Value::as_str)
+        .filter(|name| !name.trim().is_empty())
+        .ok_or_else(|| format!("{} 缺少 name 字段", MANIFEST_NAME))?
+        .to_string();
+    let frames = |key: &str| -> Result<Option<Vec<String>>, String> {
+        match root.get(key) {
+            None => Ok(None),
+            Some(Value::Array(items)) => items
+                .iter()
+                .map(|item| item.as_str().map(str::to_string).ok_or_else(|| format!("{} 中的帧必须是文件路径", key)))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Some),
+            Some(_) => Err(format!("{} 必须是文件路径数组", key)),
+        }
+    };
+
+    let light = frames("light")?.ok_or_else(|| format!("{} 缺少 light 字段", MANIFEST_NAME))?;
+    if light.is_empty() {
+        return Err("light 中至少需要 1 帧".to_string());
+    }
+    let dark = frames("dark")?;
+    if let Some(dark) = &dark {
+        if dark.len() != light.len() {
+            return Err(format!("dark 需要与 light 相同的 {} 帧，实际为 {} 帧", light.len(), dark.len()));
+        }
+    }
+
+    let weights = match root.get("weights") {
+        None => vec![1.0; light.len()],
+        Some(Value::Array(items)) => {
+            let weights = items
+                .iter()
+                // 按 f32 检查，超出 f32 范围的大数转换后为无穷大，过小的正数转换后为 0
+                .map(|item| item.as_f64().map(|w| w as f32).filter(|w| w.is_finite() && *w > 0.0))
+                .collect::<Option<Vec<f32>>>()
+                .ok_or("weights 中的时长必须是正数")?;
+            if weights.len() != light.len() {
+                return Err(format!("weights 需要与 light 相同的 {} 项，实际为 {} 项", light.len(), weights.len()));
+            }
+            weights
+        }
+        Some(_) => return Err("weights 必须是数字数组".to_string()),
+    };
+
+    Ok(Manifest { name, light, dark, weights })
+}
+
+fn load_from(mut read: impl FnMut(&str) -> Result<Vec<u8>, String>) -> Result<RunnerPack, String> {
+    let manifest = parse_manifest(&read(MANIFEST_NAME)?)?;
+    let mut load_frames = |names: &[String]| -> Result<Vec<FrameImage>, String> {
+        names.iter().map(|name| FrameImage::from_bytes(name, read(name)?)).collect()
+    };
+
+    let light = load_frames(&manifest.light)?;
+    let dark = match &manifest.dark {
+        Some(dark) => load_frames(dark)?,
+        None => load_frames(&manifest.light)?,
+    };
+    Ok(RunnerPack { name: manifest.name, light, dark, weights: manifest.weights })
+}
+
+// 拼接包内路径，拒绝绝对路径和 ..，防止读取包以外的文件
+fn safe_join(root: &Path, name: &str) -> Result<PathBuf, String> {
+    let relative = Path::new(name);
+    if relative.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+        Ok(root.join(relative))
+    } else {
+        Err(format!("不允许的路径: {}", name))
+    }
+}
+
+fn open_zip(path: &Path) -> Result<zip::ZipArchive<File>, String> {
+    let file = File::open(path).map_err(|e| format!("无法打开 {}: {}", path.display(), e))?;
+    zip::ZipArchive::new(file).map_err(|e| format!("{} 不是有效的 zip 文件: {}", path.display(), e))
+}
+
+// 清单在压缩包中的目录前缀：位于顶层时为空，位于唯一的顶层目录下时为 "目录/"
+fn zip_manifest_prefix(archive: &mut zip::ZipArchive<File>) -> Result<String, String> {
+    archive
+        .file_names()
+        .filter_map(|name| name.strip_suffix(MANIFEST_NAME))
+        .filter(|prefix| prefix.is_empty() || (prefix.ends_with('/') && prefix.matches('/').count() == 1))
+        .min_by_key(|prefix| prefix.len())
+        .map(str::to_string)
+        .ok_or_else(|| format!("压缩包中没有 {}", MANIFEST_NAME))
+}
+
+// 用户跑者包所在目录：%APPDATA%\RunCat\runners，没有 APPDATA 时使用程序所在目录下的 runners
+pub fn packs_dir() -> PathBuf {
+    match std::env::var_os("APPDATA") {
+        Some(appdata) => PathBuf::from(appdata).join("RunCat").join("runners"),
+        None => std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(|dir| dir.join("runners")))
+            .unwrap_or_else(|| PathBuf::from("runners")),
+    }
+}
+
+// 跑者包目录中的一个包
+pub struct PackInfo {
+    // 设置 RunnerPack 中使用的值：目录名或 zip 文件名
+    pub id: String,
+    pub name: String,
+}
+
+// 列出跑者包目录中的全部有效跑者包，按 id 排序
+pub fn list_packs() -> Vec<PackInfo> {
+    let entries = match std::fs::read_dir(packs_dir()) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut packs: Vec<PackInfo> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let is_pack = path.join(MANIFEST_NAME).is_file()
+                || path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("zip"));
+            if !is_pack {
+                return None;
+            }
+            let name = read_pack_name(&path).ok()?;
+            Some(PackInfo { id: entry.file_name().to_string_lossy().to_string(), name })
+        })
+        .collect();
+    packs.sort_by(|a, b| a.id.cmp(&b.id));
+    packs
+}
+
+// 解析设置中的跑者包：绝对路径直接使用，否则为跑者包目录中的名称
+pub fn resolve_pack_path(setting: &str) -> PathBuf {
+    let path = Path::new(setting);
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        packs_dir().join(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weights(json: &str) -> Result<Vec<f32>, String> {
+        let manifest = format!(r#"{{"format": 1, "name": "test", "light": ["a.png", "b.png"], "weights": {}}}"#, json);
+        parse_manifest(manifest.as_bytes()).map(|m| m.weights)
+    }
+
+    #[test]
+    fn weights_default_to_equal() {
+        let manifest = parse_manifest(br#"{"format": 1, "name": "test", "light": ["a.png", "b.png"]}"#).unwrap();
+        assert_eq!(manifest.weights, [1.0, 1.0]);
+    }
+
+    #[test]
+    fn weights_must_fit_f32() {
+        assert_eq!(weights("[1, 2.5]"), Ok(vec![1.0, 2.5]));
+        assert!(weights("[1, 1e39]").is_err());
+        assert!(weights("[1, 1e-50]").is_err());
+        assert!(weights("[1, 0]").is_err());
+        assert!(weights("[1, -1]").is_err());
+        assert!(weights("[1]").is_err());
+    }
+}
@@ -0,0 +1,68 @@
+// 进程树：启动的子进程及其之后启动的全部子孙进程，可以一起结束。
+// Windows 上为作业对象，其他平台为新的进程组
+
+use std::process::{Child, Command};
+
+#[cfg(windows)]
+pub struct ProcessTree {
+    job: winapi::um::winnt::HANDLE,
+}
+
+// 作业对象句柄可以在任意线程中使用和关闭
+#[cfg(windows)]
+unsafe impl Send for ProcessTree {}
+
+#[cfg(windows)]
+impl ProcessTree {
+    // 启动命令并把它放入新的作业；创建或加入作业失败时 job 为空，结束时只结束子进程本身
+    pub fn spawn(command: &mut Command) -> std::io::Result<(Child, ProcessTree)> {
+        use std::os::windows::io::AsRawHandle;
+        use winapi::um::jobapi2::{AssignProcessToJobObject, CreateJobObjectW};
+
+        let child = command.spawn()?;
+        let mut job = unsafe { CreateJobObjectW(std::ptr::null_mut(), std::ptr::null()) };
+        if !job.is_null() && unsafe { AssignProcessToJobObject(job, child.as_raw_handle() as _) } == 0 {
+            unsafe { winapi::um::handleapi::CloseHandle(job) };
+            job = std::ptr::null_mut();
+        }
+        Ok((child, ProcessTree { job }))
+    }
+
+    pub fn kill(&self, child: &mut Child) {
+        if self.job.is_null() || unsafe { winapi::um::jobapi2::TerminateJobObject(self.job, 1) } == 0 {
+            let _ = child.kill();
+        }
+    }
+}
+
+#[cfg(windows)]
+impl Drop for ProcessTree {
+    fn drop(&mut self) {
+        if !self.job.is_null() {
+            unsafe { winapi::um::handleapi::CloseHandle(self.job) };
+        }
+    }
+}
+
+#[cfg(not(windows))]
+pub struct ProcessTree {
+    pgid: i32,
+}
+
+#[cfg(not(windows))]
+impl ProcessTree {
+    // 启动命令并放入以它为首的新进程组
+    pub fn spawn(command: &mut Command) -> std::io::Result<(Child, ProcessTree)> {
+        use std::os::unix::process::CommandExt;
+
+        let child = command.process_group(0).spawn()?;
+        let pgid = child.id() as i32;
+        Ok((child, ProcessTree { pgid }))
+    }
+
+    pub fn kill(&self, child: &mut Child) {
+        if unsafe { libc::killpg(self.pgid, libc::SIGKILL) } != 0 {
+            let _ = child.kill();
+        }
+    }
+}
@@ -0,0 +1,349 @@
+// 跑者模块：托盘中奔跑的角色。内置小猫来自编译进程序的图标资源，其他跑者在运行时从跑者包读取
+
+mod overlay;
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use winapi::shared::minwindef::{HINSTANCE, TRUE};
+use winapi::shared::windef::{HBITMAP, HICON};
+use winapi::um::wingdi::{CreateBitmap, CreateDIBSection, DeleteObject, GetDIBits, GetObjectW, BITMAP, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, DIB_RGB_COLORS};
+use winapi::um::winuser::{CreateIconFromResourceEx, CreateIconIndirect, DestroyIcon, GetDC, GetIconInfo, GetSystemMetrics, ReleaseDC, ICONINFO, LR_DEFAULTCOLOR, SM_CXSMICON};
+
+pub use overlay::overlay_options;
+pub use run_cat::pack::import::{import_pack, ImportOptions, SpriteLayout};
+pub use run_cat::pack::list_packs;
+use overlay::OverlayStyle;
+use run_cat::pack::{decode_png, load_pack, resolve_pack_path, select_ico_image, FrameImage, Rgba};
+
+use crate::constants::{BUILTIN_FRAME_COUNT, IDI_DARKCAT_0, IDI_LIGHTCAT_0};
+use crate::updater::Theme;
+use crate::utils::load_icon;
+
+// 菜单请求重新读取跑者包，主循环在下一次更新图标前处理
+static RELOAD_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+pub fn request_reload() {
+    RELOAD_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+// 取出并清除重新加载请求
+pub fn take_reload_request() -> bool {
+    RELOAD_REQUESTED.swap(false, Ordering::SeqCst)
+}
+
+// 当前跑者各帧的相对显示时长，主线程加载跑者时写入，动画线程据此决定帧数和每帧时长；
+// 尚未加载跑者时为空
+static FRAME_WEIGHTS: Mutex<Vec<f32>> = Mutex::new(Vec::new());
+
+// 当前跑者各帧的相对显示时长，尚未加载跑者时按内置小猫的帧数返回相同时长
+pub fn frame_weights() -> Vec<f32> {
+    let weights = FRAME_WEIGHTS.lock().unwrap();
+    if weights.is_empty() {
+        vec![1.0; BUILTIN_FRAME_COUNT]
+    } else {
+        weights.clone()
+    }
+}
+
+// 叠加数值的图标缓存最多保留的图标数，避免占用过多 GDI 句柄
+const OVERLAY_CACHE_MAX: usize = 2048;
+
+// 已加载的跑者：浅色、深色两组帧图标及各帧时长
+pub struct Runner {
+    // 创建时使用的 RunnerPack 设置值，空为内置小猫
+    setting: String,
+    light: Vec<HICON>,
+    dark: Vec<HICON>,
+    // 各帧的相对显示时长
+    weights: Vec<f32>,
+    // 从跑者包创建的图标需要销毁，内置资源图标由系统管理
+    owned: bool,
+    overlay: OverlayStyle,
+    // 叠加了数值的图标，键为 (配色, 帧序号, 显示的数值)；创建失败时为空句柄，使用原帧
+    overlay_cache: HashMap<(Theme, usize, u32), HICON>,
+}
+
+impl Runner {
+    // 编译进程序的内置小猫
+    pub fn builtin(hinstance: HINSTANCE) -> Runner {
+        let frames = |first: u16| (0..BUILTIN_FRAME_COUNT as u16).map(|i| load_icon(hinstance, first + i)).collect();
+        Runner {
+            setting: String::new(),
+            light: frames(IDI_LIGHTCAT_0),
+            dark: frames(IDI_DARKCAT_0),
+            weights: vec![1.0; BUILTIN_FRAME_COUNT],
+            owned: false,
+            overlay: OverlayStyle::from_settings(),
+            overlay_cache: HashMap::new(),
+        }
+    }
+
+    // 从目录或 zip 形式的跑者包创建
+    pub fn load(path: &Path) -> Result<Runner, String> {
+        let pack = load_pack(path)?;
+        let mut runner = Runner {
+            setting: String::new(),
+            light: Vec::new(),
+            dark: Vec::new(),
+            weights: pack.weights.clone(),
+            owned: true,
+            overlay: OverlayStyle::from_settings(),
+            overlay_cache: HashMap::new(),
+        };
+        // 中途失败时 runner 被丢弃，已创建的图标随之销毁
+        for (i, frame) in pack.light.iter().enumerate() {
+            runner.light.push(create_icon(frame).map_err(|e| format!("{} 浅色第 {} 帧: {}", pack.name, i + 1, e))?);
+        }
+        for (i, frame) in pack.dark.iter().enumerate() {
+            runner.dark.push(create_icon(frame).map_err(|e| format!("{} 深色第 {} 帧: {}", pack.name, i + 1, e))?);
+        }
+        Ok(runner)
+    }
+
+    // 按设置 RunnerPack 创建，读取失败时回退到内置小猫；同时把各帧时长提供给动画线程
+    pub fn from_settings(hinstance: HINSTANCE) -> Runner {
+        let setting = crate::utils::get_runner_pack();
+        let mut runner = if setting.is_empty() {
+            Runner::builtin(hinstance)
+        } else {
+            let path = resolve_pack_path(&setting);
+            Runner::load(&path).unwrap_or_else(|e| {
+                eprintln!("无法加载跑者包 {}: {}，使用内置小猫", path.display(), e);
+                Runner::builtin(hinstance)
+            })
+        };
+        // 记录设置值，加载失败时也不会在每次设置变化时反复重试
+        runner.setting = setting;
+        *FRAME_WEIGHTS.lock().unwrap() = runner.weights.clone();
+        runner
+    }
+
+    pub fn setting(&self) -> &str {
+        &self.setting
+    }
+
+    // 指定配色和帧序号的图标
+    pub fn icon(&self, theme: Theme, frame: usize) -> HICON {
+        let frames = match theme {
+            Theme::Light => &self.light,
+            Theme::Dark => &self.dark,
+        };
+        frames[frame % frames.len()]
+    }
+
+    // 指定帧叠加指标值（0-100）后的图标；未开启数值叠加或没有数值时返回原帧。
+    // 显示相同数值的图标只绘制一次
+    pub fn icon_with_value(&mut self, theme: Theme, frame: usize, percent: Option<f32>) -> HICON {
+        let base = self.icon(theme, frame);
+        let (placement, percent) = match (self.overlay.placement, percent) {
+            (Some(placement), Some(percent)) => (placement, percent),
+            _ => return base,
+        };
+        let frame = frame % self.light.len();
+        let value = self.overlay.display_value(percent);
+        let key = (theme, frame, value);
+
+        if !self.overlay_cache.contains_key(&key) {
+            // 缓存能容纳当前配色下每帧的每个数值；切换配色后丢弃另一配色的图标，
+            // 超过上限时只保留当前数值的各帧，动画循环中不会反复重绘
+            let limit = (self.light.len() * self.overlay.value_count()).min(OVERLAY_CACHE_MAX);
+            let full = self.overlay_cache.len() >= limit;
+            self.evict_overlay(|&(cached_theme, _, cached_value)| cached_theme != theme || (full && cached_value != value));
+            let contrast = self.overlay.contrast;
+            let icon = icon_to_rgba(base)
+                .and_then(|mut image| {
+                    overlay::draw_value(&mut image, value, placement, contrast, theme == Theme::Dark);
+                    create_icon_from_rgba(&image)
+                })
+                .unwrap_or_else(|e| {
+                    eprintln!("无法绘制图标数值: {}", e);
+                    null_mut()
+                });
+            self.overlay_cache.insert(key, icon);
+        }
+
+        match self.overlay_cache[&key] {
+            icon if icon.is_null() => base,
+            icon => icon,
+        }
+    }
+
+    // 重新读取数值叠加设置，发生变化时丢弃已绘制的图标
+    pub fn refresh_overlay(&mut self) {
+        let overlay = OverlayStyle::from_settings();
+        if overlay != self.overlay {
+            self.overlay = overlay;
+            self.clear_overlay_cache();
+        }
+    }
+
+    fn clear_overlay_cache(&mut self) {
+        self.evict_overlay(|_| true);
+    }
+
+    // 销毁并移除满足条件的缓存图标
+    fn evict_overlay(&mut self, evict: impl Fn(&(Theme, usize, u32)) -> bool) {
+        self.overlay_cache.retain(|key, &mut icon| {
+            let keep = !evict(key);
+            if !keep && !icon.is_null() {
+                unsafe {
+                    DestroyIcon(icon);
+                }
+            }
+            keep
+        });
+    }
+}
+
+impl Drop for Runner {
+    fn drop(&mut self) {
+        self.clear_overlay_cache();
+        if self.owned {
+            for &icon in self.light.iter().chain(self.dark.iter()) {
+                unsafe {
+                    DestroyIcon(icon);
+                }
+            }
+        }
+    }
+}
+
+fn create_icon(frame: &FrameImage) -> Result<HICON, String> {
+    match frame {
+        FrameImage::Png(bytes) => create_icon_from_png(bytes),
+        FrameImage::Ico(bytes) => create_icon_from_ico(bytes),
+    }
+}
+
+// 用 PNG 的 RGBA 像素创建带透明通道的图标
+fn create_icon_from_png(bytes: &[u8]) -> Result<HICON, String> {
+    create_icon_from_rgba(&decode_png(bytes)?)
+}
+
+// 用 RGBA 像素创建带透明通道的图标
+fn create_icon_from_rgba(image: &Rgba) -> Result<HICON, String> {
+    let (width, height) = (image.width as i32, image.height as i32);
+
+    unsafe {
+        let info = dib_info(width, height);
+        let mut bits = null_mut();
+        let color: HBITMAP = CreateDIBSection(null_mut(), &info, DIB_RGB_COLORS, &mut bits, null_mut(), 0);
+        if color.is_null() {
+            return Err("无法创建位图".to_string());
+        }
+        // RGBA 转为 Windows 使用的 BGRA
+        let pixels = std::slice::from_raw_parts_mut(bits as *mut u8, image.pixels.len());
+        for (dst, src) in pixels.chunks_exact_mut(4).zip(image.pixels.chunks_exact(4)) {
+            dst.copy_from_slice(&[src[2], src[1], src[0], src[3]]);
+        }
+
+        // 全 0 的掩码，透明度完全由颜色位图的 alpha 通道决定；单色位图每行按 2 字节对齐
+        let mask_bits = vec![0u8; ((width as usize).div_ceil(16) * 2) * height as usize];
+        let mask = CreateBitmap(width, height, 1, 1, mask_bits.as_ptr() as *const _);
+
+        let mut icon_info = ICONINFO {
+            fIcon: TRUE,
+            xHotspot: 0,
+            yHotspot: 0,
+            hbmMask: mask,
+            hbmColor: color,
+        };
+        let icon = CreateIconIndirect(&mut icon_info);
+        DeleteObject(color as *mut _);
+        DeleteObject(mask as *mut _);
+
+        if icon.is_null() {
+            Err("无法创建图标".to_string())
+        } else {
+            Ok(icon)
+        }
+    }
+}
+
+// 从 ICO 文件中选择最接近托盘图标尺寸的一项创建图标
+fn create_icon_from_ico(bytes: &[u8]) -> Result<HICON, String> {
+    let wanted = unsafe { GetSystemMetrics(SM_CXSMICON) }.max(16) as u32;
+    let (width, data) = select_ico_image(bytes, wanted)?;
+    let icon = unsafe {
+        CreateIconFromResourceEx(
+            data.as_ptr() as *mut u8,
+            data.len() as u32,
+            TRUE,
+            0x0003_0000,
+            width as i32,
+            width as i32,
+            LR_DEFAULTCOLOR,
+        )
+    };
+    if icon.is_null() {
+        Err("无法创建图标".to_string())
+    } else {
+        Ok(icon)
+    }
+}
+
+// 32 位自上而下的 DIB 描述
+fn dib_info(width: i32, height: i32) -> BITMAPINFO {
+    let mut info: BITMAPINFO = unsafe { std::mem::zeroed() };
+    info.bmiHeader.biSize = std::mem::size_of::<BITMAPINFOHEADER>() as u32;
+    info.bmiHeader.biWidth = width;
+    // 负高度表示自上而下的行顺序，与 PNG 一致
+    info.bmiHeader.biHeight = -height;
+    info.bmiHeader.biPlanes = 1;
+    info.bmiHeader.biBitCount = 32;
+    info.bmiHeader.biCompression = BI_RGB;
+    info
+}
+
+// 读取图标的 RGBA 像素；没有透明通道的旧式图标按掩码生成透明度
+fn icon_to_rgba(icon: HICON) -> Result<Rgba, String> {
+    unsafe {
+        let mut icon_info: ICONINFO = std::mem::zeroed();
+        if GetIconInfo(icon, &mut icon_info) == 0 {
+            return Err("无法读取图标".to_string());
+        }
+        let result = read_icon_bitmaps(&icon_info);
+        if !icon_info.hbmColor.is_null() {
+            DeleteObject(icon_info.hbmColor as *mut _);
+        }
+        DeleteObject(icon_info.hbmMask as *mut _);
+        result
+    }
+}
+
+unsafe fn read_icon_bitmaps(icon_info: &ICONINFO) -> Result<Rgba, String> {
+    if icon_info.hbmColor.is_null() {
+        return Err("不支持单色图标".to_string());
+    }
+    let mut bitmap: BITMAP = std::mem::zeroed();
+    if GetObjectW(icon_info.hbmColor as *mut _, std::mem::size_of::<BITMAP>() as i32, &mut bitmap as *mut _ as *mut _) == 0 {
+        return Err("无法读取图标位图".to_string());
+    }
+    let (width, height) = (bitmap.bmWidth, bitmap.bmHeight);
+
+    let hdc = GetDC(null_mut());
+    let read = |hbitmap: HBITMAP| -> Option<Vec<u8>> {
+        let mut info = dib_info(width, height);
+        let mut pixels = vec![0u8; (width * height * 4) as usize];
+        let lines = GetDIBits(hdc, hbitmap, 0, height as u32, pixels.as_mut_ptr() as *mut _, &mut info, DIB_RGB_COLORS);
+        (lines == height).then_some(pixels)
+    };
+    let color = read(icon_info.hbmColor);
+    let mask = read(icon_info.hbmMask);
+    ReleaseDC(null_mut(), hdc);
+
+    let mut pixels = color.ok_or("无法读取图标像素")?;
+    let has_alpha = pixels.chunks_exact(4).any(|p| p[3] != 0);
+    for (i, pixel) in pixels.chunks_exact_mut(4).enumerate() {
+        // BGRA 转为 RGBA；掩码为白色的像素透明
+        pixel.swap(0, 2);
+        if !has_alpha {
+            let transparent = mask.as_ref().is_some_and(|mask| mask[i * 4] != 0);
+            pixel[3] = if transparent { 0 } else { 255 };
+        }
+    }
+    Ok(Rgba { width: width as u32, height: height as u32, pixels })
+}
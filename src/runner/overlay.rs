@@ -0,0 +1,324 @@
+// 数值叠加：在跑者的帧图像上用内置点阵字体绘制驱动动画的指标值（0-100）
+//
+// 只处理 RGBA 像素，不依赖 Windows API。字体为 3×5 点阵，16 像素图标按原大小绘制，
+// 更大的图标按整数倍放大；文字与跑者配色一致，浅色模式为黑字白边，深色模式为白字黑边
+
+use run_cat::pack::Rgba;
+use crate::metrics::MetricOptions;
+
+// 数字 0-9 的点阵，每行 3 位，高位在左
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b011, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+const GLYPH_WIDTH: u32 = 3;
+const GLYPH_HEIGHT: u32 = 5;
+
+// 菜单中列出的叠加位置 (显示名称, 设置值)
+pub const PLACEMENT_PRESETS: &[(&str, &str)] = &[
+    ("不显示", "off"),
+    ("右下角", "bottom-right"),
+    ("左下角", "bottom-left"),
+    ("右上角", "top-right"),
+    ("左上角", "top-left"),
+    ("居中", "center"),
+];
+
+// 菜单中列出的对比方式 (显示名称, 设置值)
+pub const CONTRAST_PRESETS: &[(&str, &str)] = &[
+    ("描边", "outline"),
+    ("底色", "backdrop"),
+    ("无", "none"),
+];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Placement {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Center,
+}
+
+// 文字与帧图像的区分方式
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Contrast {
+    None,
+    // 文字四周 1 像素的反色描边
+    Outline,
+    // 文字下方的反色矩形
+    Backdrop,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct OverlayStyle {
+    // 为 None 时不叠加数值
+    pub placement: Option<Placement>,
+    pub contrast: Contrast,
+    // 数值取整到该宽度的倍数后显示，为 1 时显示精确值；显示相同数值的图标只绘制一次
+    pub bucket: u32,
+}
+
+impl OverlayStyle {
+    // 解析设置值，无法识别的位置视为不显示，无法识别的对比方式视为描边
+    pub fn parse(placement: &str, contrast: &str, bucket: u32) -> OverlayStyle {
+        let placement = match placement.trim() {
+            "top-left" => Some(Placement::TopLeft),
+            "top-right" => Some(Placement::TopRight),
+            "bottom-left" => Some(Placement::BottomLeft),
+            "bottom-right" => Some(Placement::BottomRight),
+            "center" => Some(Placement::Center),
+            _ => None,
+        };
+        let contrast = match contrast.trim() {
+            "none" => Contrast::None,
+            "backdrop" => Contrast::Backdrop,
+            _ => Contrast::Outline,
+        };
+        OverlayStyle { placement, contrast, bucket: bucket.clamp(1, 100) }
+    }
+
+    pub fn from_settings() -> OverlayStyle {
+        OverlayStyle::parse(
+            &crate::utils::get_value_overlay(),
+            &crate::utils::get_overlay_contrast(),
+            crate::utils::get_overlay_bucket(),
+        )
+    }
+
+    // 图标上显示的数值：指标值（0-100）取整到最近的 bucket 倍数，100 总是可以显示
+    pub fn display_value(&self, percent: f32) -> u32 {
+        let value = percent.clamp(0.0, 100.0).round() as u32;
+        let lower = value / self.bucket * self.bucket;
+        let upper = (lower + self.bucket).min(100);
+        if value - lower < upper - value {
+            lower
+        } else {
+            upper
+        }
+    }
+
+    // 可能显示的数值个数：0 到 100 间 bucket 的各倍数，以及 100
+    pub fn value_count(&self) -> usize {
+        (100 / self.bucket + 1) as usize + usize::from(100 % self.bucket != 0)
+    }
+}
+
+// 在图像上绘制数字；dark 为 true 时使用深色模式的配色
+pub fn draw_value(image: &mut Rgba, value: u32, placement: Placement, contrast: Contrast, dark: bool) {
+    let digits: Vec<usize> = value.to_string().bytes().map(|b| (b - b'0') as usize).collect();
+    let scale = (image.width.min(image.height) / 16).max(1);
+    let text_width = (digits.len() as u32 * (GLYPH_WIDTH + 1) - 1) * scale;
+    let text_height = GLYPH_HEIGHT * scale;
+    // 描边和底色向外延伸 1 像素，位置计算时留出该边距
+    let margin = if contrast == Contrast::None { 0 } else { 1 };
+
+    let left_edge = margin;
+    let right_edge = image.width.saturating_sub(text_width + margin);
+    let top_edge = margin;
+    let bottom_edge = image.height.saturating_sub(text_height + margin);
+    let (x0, y0) = match placement {
+        Placement::TopLeft => (left_edge, top_edge),
+        Placement::TopRight => (right_edge, top_edge),
+        Placement::BottomLeft => (left_edge, bottom_edge),
+        Placement::BottomRight => (right_edge, bottom_edge),
+        Placement::Center => (
+            image.width.saturating_sub(text_width) / 2,
+            image.height.saturating_sub(text_height) / 2,
+        ),
+    };
+
+    let (ink, paper) = if dark {
+        ([255, 255, 255, 255], [0, 0, 0, 255])
+    } else {
+        ([0, 0, 0, 255], [255, 255, 255, 255])
+    };
+
+    // 文字覆盖的像素
+    let mut lit = Vec::new();
+    for (i, &digit) in digits.iter().enumerate() {
+        let glyph_x = x0 + i as u32 * (GLYPH_WIDTH + 1) * scale;
+        for (row, bits) in DIGITS[digit].iter().enumerate() {
+            for col in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - col)) == 0 {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        lit.push((glyph_x + col * scale + dx, y0 + row as u32 * scale + dy));
+                    }
+                }
+            }
+        }
+    }
+
+    match contrast {
+        Contrast::None => {}
+        Contrast::Outline => {
+            for &(x, y) in &lit {
+                for ny in y.saturating_sub(1)..=y + 1 {
+                    for nx in x.saturating_sub(1)..=x + 1 {
+                        set_pixel(image, nx, ny, paper);
+                    }
+                }
+            }
+        }
+        Contrast::Backdrop => {
+            for y in y0.saturating_sub(1)..y0 + text_height + 1 {
+                for x in x0.saturating_sub(1)..x0 + text_width + 1 {
+                    set_pixel(image, x, y, paper);
+                }
+            }
+        }
+    }
+    for &(x, y) in &lit {
+        set_pixel(image, x, y, ink);
+    }
+}
+
+fn set_pixel(image: &mut Rgba, x: u32, y: u32, color: [u8; 4]) {
+    if x < image.width && y < image.height {
+        let i = ((y * image.width + x) * 4) as usize;
+        image.pixels[i..i + 4].copy_from_slice(&color);
+    }
+}
+
+// 菜单中的数值叠加选项
+pub fn overlay_options() -> Vec<MetricOptions> {
+    let presets = |presets: &[(&str, &str)]| presets.iter().map(|(label, value)| (label.to_string(), value.to_string())).collect();
+    vec![
+        MetricOptions {
+            title: "图标数值",
+            setting: "ValueOverlay".to_string(),
+            current: crate::utils::get_value_overlay(),
+            choices: presets(PLACEMENT_PRESETS),
+        },
+        MetricOptions {
+            title: "数值对比",
+            setting: "OverlayContrast".to_string(),
+            current: crate::utils::get_overlay_contrast(),
+            choices: presets(CONTRAST_PRESETS),
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: [u8; 4] = [0, 0, 0, 255];
+    const WHITE: [u8; 4] = [255, 255, 255, 255];
+
+    fn blank(size: u32) -> Rgba {
+        Rgba { width: size, height: size, pixels: vec![0; (size * size * 4) as usize] }
+    }
+
+    // 指定颜色像素的外接矩形 (左, 上, 右, 下)
+    fn bounds(image: &Rgba, color: [u8; 4]) -> Option<(u32, u32, u32, u32)> {
+        let points: Vec<(u32, u32)> = image
+            .pixels
+            .chunks_exact(4)
+            .enumerate()
+            .filter(|(_, pixel)| *pixel == color)
+            .map(|(i, _)| (i as u32 % image.width, i as u32 / image.width))
+            .collect();
+        Some((
+            points.iter().map(|p| p.0).min()?,
+            points.iter().map(|p| p.1).min()?,
+            points.iter().map(|p| p.0).max()?,
+            points.iter().map(|p| p.1).max()?,
+        ))
+    }
+
+    #[test]
+    fn rounds_to_bucket() {
+        let style = |bucket| OverlayStyle::parse("bottom-right", "outline", bucket);
+        let values = |bucket, percents: &[f32]| percents.iter().map(|&p| style(bucket).display_value(p)).collect::<Vec<_>>();
+        assert_eq!(values(1, &[73.0, 99.4, 99.6, 100.0, -5.0, 150.0, f32::NAN]), [73, 99, 100, 100, 0, 100, 0]);
+        assert_eq!(values(5, &[72.0, 73.0, 97.0, 99.0, 100.0]), [70, 75, 95, 100, 100]);
+        assert_eq!(values(30, &[14.0, 15.0, 74.0, 80.0, 95.0, 100.0]), [0, 30, 60, 90, 100, 100]);
+
+        assert_eq!(style(1).value_count(), 101);
+        assert_eq!(style(5).value_count(), 21);
+        assert_eq!(style(30).value_count(), 5);
+        assert_eq!(style(100).value_count(), 2);
+        assert_eq!(style(0).value_count(), 101);
+    }
+
+    #[test]
+    fn draws_digit_pixels() {
+        let mut image = blank(16);
+        draw_value(&mut image, 47, Placement::TopLeft, Contrast::None, false);
+        for y in 0..16 {
+            for x in 0..16 {
+                let digit = x / (GLYPH_WIDTH + 1);
+                let col = x % (GLYPH_WIDTH + 1);
+                let expected = digit < 2
+                    && col < GLYPH_WIDTH
+                    && y < GLYPH_HEIGHT
+                    && DIGITS[[4, 7][digit as usize]][y as usize] & (1 << (GLYPH_WIDTH - 1 - col)) != 0;
+                let i = ((y * 16 + x) * 4) as usize;
+                let pixel = &image.pixels[i..i + 4];
+                assert_eq!(pixel == BLACK, expected, "({}, {})", x, y);
+                assert!(pixel == BLACK || pixel == [0, 0, 0, 0]);
+            }
+        }
+
+        // 深色模式为白字；放大时每个点为 scale × scale 的方块
+        let mut image = blank(32);
+        draw_value(&mut image, 1, Placement::TopLeft, Contrast::None, true);
+        assert_eq!(bounds(&image, WHITE), Some((0, 0, 5, 9)));
+        assert_eq!(image.pixels[..4], [0, 0, 0, 0]);
+        assert_eq!(image.pixels[(2 * 4)..(2 * 4 + 4)], WHITE);
+        assert_eq!(image.pixels[(3 * 4)..(3 * 4 + 4)], WHITE);
+    }
+
+    #[test]
+    fn places_text_in_corners() {
+        // (位置, 16 像素时文字的外接矩形, 32 像素时的外接矩形)；描边占 1 像素边距
+        let cases = [
+            (Placement::TopLeft, (1, 1, 3, 5), (1, 1, 6, 10)),
+            (Placement::TopRight, (12, 1, 14, 5), (25, 1, 30, 10)),
+            (Placement::BottomLeft, (1, 10, 3, 14), (1, 21, 6, 30)),
+            (Placement::BottomRight, (12, 10, 14, 14), (25, 21, 30, 30)),
+            (Placement::Center, (6, 5, 8, 9), (13, 11, 18, 20)),
+        ];
+        for (placement, small, large) in cases {
+            for (size, expected) in [(16, small), (32, large)] {
+                let mut image = blank(size);
+                draw_value(&mut image, 8, placement, Contrast::Outline, false);
+                assert_eq!(bounds(&image, BLACK), Some(expected), "{:?} {}", placement, size);
+                let (left, top, right, bottom) = expected;
+                let outline = (left - 1, top - 1, right + 1, bottom + 1);
+                assert_eq!(bounds(&image, WHITE), Some(outline), "{:?} {}", placement, size);
+            }
+        }
+    }
+
+    #[test]
+    fn hundred_fits_small_icons() {
+        for size in [16, 8, 3] {
+            for (placement, contrast) in [Placement::TopLeft, Placement::TopRight, Placement::BottomLeft, Placement::BottomRight, Placement::Center]
+                .into_iter()
+                .flat_map(|p| [Contrast::None, Contrast::Outline, Contrast::Backdrop].map(|c| (p, c)))
+            {
+                let mut image = blank(size);
+                draw_value(&mut image, 100, placement, contrast, false);
+                assert_eq!(image.pixels.len(), (size * size * 4) as usize);
+            }
+        }
+
+        let mut image = blank(16);
+        draw_value(&mut image, 100, Placement::BottomRight, Contrast::Backdrop, false);
+        assert_eq!(bounds(&image, BLACK), Some((4, 10, 14, 14)));
+        assert_eq!(bounds(&image, WHITE), Some((3, 9, 15, 15)));
+    }
+}
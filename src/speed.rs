@@ -0,0 +1,240 @@
+// 速度曲线模块：把 0-100 的指标值换算成动画帧间隔
+//
+// 曲线先把指标值映射为 0-1 的速度，再在最慢、最快帧间隔之间按帧率插值：
+// 速度 0 为 FrameMaxMs，速度 1 为 FrameMinMs。按帧率而不是按间隔插值，使速度的变化在视觉上均匀
+
+use std::time::Duration;
+
+use crate::metrics::MetricOptions;
+
+// 菜单中列出的曲线 (显示名称, 设置值)
+pub const CURVE_PRESETS: &[(&str, &str)] = &[
+    ("线性", "linear"),
+    ("对数（低负载更灵敏）", "log"),
+    ("指数（高负载更灵敏）", "exp"),
+    ("分段（区分高负载）", "table:0=0,50=15,80=45,100=100"),
+];
+
+// 菜单中列出的停止阈值 (显示名称, 设置值)
+pub const STOP_PRESETS: &[(&str, &str)] = &[
+    ("不停止", "0"),
+    ("低于 2%", "2"),
+    ("低于 5%", "5"),
+    ("低于 10%", "10"),
+    ("低于 20%", "20"),
+];
+
+// 对数、指数曲线未指定弯曲程度时使用的默认值
+const DEFAULT_LOG_K: f32 = 9.0;
+const DEFAULT_EXP_K: f32 = 3.0;
+// 弯曲程度的上限：再大时 e^k 接近 f32 的上限，曲线也已几乎是阶跃
+const MAX_K: f32 = 50.0;
+
+// 指标值到速度的映射，输入、输出均为 0-1
+#[derive(Clone, Debug, PartialEq)]
+pub enum Curve {
+    Linear,
+    // ln(1 + k·x) / ln(1 + k)，k 越大低负载段越灵敏
+    Log(f32),
+    // (e^(k·x) - 1) / (e^k - 1)，k 越大高负载段越灵敏
+    Exp(f32),
+    // 按 (指标值, 速度) 的折线插值，点按指标值升序排列
+    Table(Vec<(f32, f32)>),
+}
+
+impl Curve {
+    // 解析设置值：linear、log[:k]、exp[:k]、table:指标值=速度,...（均为 0-100）
+    pub fn parse(spec: &str) -> Result<Curve, String> {
+        let spec = spec.trim();
+        let (kind, arg) = match spec.split_once(':') {
+            Some((kind, arg)) => (kind.trim(), Some(arg.trim())),
+            None => (spec, None),
+        };
+        let parse_k = |default: f32| -> Result<f32, String> {
+            match arg {
+                None => Ok(default),
+                Some(arg) => arg
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|k| *k > 0.0 && *k <= MAX_K)
+                    .ok_or_else(|| format!("曲线参数必须是 0-{} 之间的正数: {}", MAX_K, arg)),
+            }
+        };
+
+        match kind {
+            "" | "linear" => Ok(Curve::Linear),
+            "log" => parse_k(DEFAULT_LOG_K).map(Curve::Log),
+            "exp" => parse_k(DEFAULT_EXP_K).map(Curve::Exp),
+            "table" => parse_table(arg.unwrap_or("")).map(Curve::Table),
+            _ => Err(format!("未知的速度曲线: {}", spec)),
+        }
+    }
+
+    // 把 0-1 的指标值映射为 0-1 的速度
+    pub fn apply(&self, x: f32) -> f32 {
+        let x = x.clamp(0.0, 1.0);
+        let y = match self {
+            Curve::Linear => x,
+            Curve::Log(k) => (1.0 + k * x).ln() / (1.0 + k).ln(),
+            Curve::Exp(k) => (k * x).exp_m1() / k.exp_m1(),
+            Curve::Table(points) => interpolate(points, x),
+        };
+        y.clamp(0.0, 1.0)
+    }
+}
+
+// 解析分段表 "0=0,50=15,100=100"，换算到 0-1
+fn parse_table(text: &str) -> Result<Vec<(f32, f32)>, String> {
+    let mut points = Vec::new();
+    for item in text.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+        let parsed = item
+            .split_once('=')
+            .and_then(|(x, y)| Some((x.trim().parse::<f32>().ok()?, y.trim().parse::<f32>().ok()?)));
+        match parsed {
+            Some((x, y)) if (0.0..=100.0).contains(&x) && (0.0..=100.0).contains(&y) => points.push((x / 100.0, y / 100.0)),
+            _ => return Err(format!("分段表项无效: {}（应为 0-100 的 指标值=速度）", item)),
+        }
+    }
+    if points.len() < 2 {
+        return Err("分段表至少需要 2 个点".to_string());
+    }
+    points.sort_by(|a, b| a.0.total_cmp(&b.0));
+    if points.windows(2).any(|w| w[0].0 == w[1].0) {
+        return Err("分段表中的指标值不能重复".to_string());
+    }
+    Ok(points)
+}
+
+// 折线插值，超出首尾点的部分取首尾点的速度
+fn interpolate(points: &[(f32, f32)], x: f32) -> f32 {
+    let index = points.partition_point(|p| p.0 <= x);
+    if index == 0 {
+        return points[0].1;
+    }
+    if index == points.len() {
+        return points[points.len() - 1].1;
+    }
+    let (x0, y0) = points[index - 1];
+    let (x1, y1) = points[index];
+    y0 + (y1 - y0) * (x - x0) / (x1 - x0)
+}
+
+// 按设置组合的速度曲线
+pub struct SpeedCurve {
+    curve: Curve,
+    min_ms: u32,
+    max_ms: u32,
+    // 指标值低于该百分比时跑者停下
+    stop_below: f32,
+}
+
+impl SpeedCurve {
+    // 按设置创建，曲线无效时输出原因并使用线性曲线
+    pub fn from_settings() -> SpeedCurve {
+        let spec = crate::utils::get_speed_curve();
+        let curve = Curve::parse(&spec).unwrap_or_else(|e| {
+            eprintln!("{}，使用线性曲线", e);
+            Curve::Linear
+        });
+        let (min_ms, max_ms) = crate::utils::get_frame_interval_ms();
+        SpeedCurve {
+            curve,
+            min_ms,
+            max_ms,
+            stop_below: crate::utils::get_stop_below_percent(),
+        }
+    }
+
+    // 指标值（0-100）对应的帧间隔；低于停止阈值时返回 None，跑者停下
+    pub fn interval(&self, percent: f32) -> Option<Duration> {
+        if percent < self.stop_below {
+            return None;
+        }
+        let speed = self.curve.apply(percent / 100.0);
+        let slowest = 1.0 / self.max_ms as f32;
+        let fastest = 1.0 / self.min_ms as f32;
+        let rate = slowest + (fastest - slowest) * speed;
+        // 曲线结果异常时按最慢速度，不让无效的时长传给 Duration
+        if !rate.is_finite() || rate <= 0.0 {
+            return Some(self.idle_interval());
+        }
+        Some(Duration::from_secs_f32(1.0 / rate / 1000.0))
+    }
+
+    // 跑者停下时检查指标的间隔
+    pub fn idle_interval(&self) -> Duration {
+        Duration::from_millis(self.max_ms as u64)
+    }
+}
+
+// 菜单中的速度曲线和停止阈值选项
+pub fn speed_options() -> Vec<MetricOptions> {
+    let presets = |presets: &[(&str, &str)]| presets.iter().map(|(label, value)| (label.to_string(), value.to_string())).collect();
+    vec![
+        MetricOptions {
+            title: "速度曲线",
+            setting: "SpeedCurve".to_string(),
+            current: crate::utils::get_speed_curve(),
+            choices: presets(CURVE_PRESETS),
+        },
+        MetricOptions {
+            title: "停止阈值",
+            setting: "StopBelowPercent".to_string(),
+            current: crate::utils::get_stop_below_percent().to_string(),
+            choices: presets(STOP_PRESETS),
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn speed_curve(curve: Curve) -> SpeedCurve {
+        SpeedCurve { curve, min_ms: 50, max_ms: 200, stop_below: 5.0 }
+    }
+
+    #[test]
+    fn parses_curves() {
+        assert_eq!(Curve::parse(""), Ok(Curve::Linear));
+        assert_eq!(Curve::parse("log"), Ok(Curve::Log(DEFAULT_LOG_K)));
+        assert_eq!(Curve::parse("exp:5"), Ok(Curve::Exp(5.0)));
+        assert_eq!(Curve::parse("table:100=100, 0=0"), Ok(Curve::Table(vec![(0.0, 0.0), (1.0, 1.0)])));
+        assert!(Curve::parse("table:0=0").is_err());
+        assert!(Curve::parse("table:0=0,0=50").is_err());
+        assert!(Curve::parse("steps").is_err());
+    }
+
+    #[test]
+    fn rejects_steep_or_invalid_k() {
+        assert_eq!(Curve::parse("exp:50"), Ok(Curve::Exp(50.0)));
+        assert!(Curve::parse("exp:100").is_err());
+        assert!(Curve::parse("log:1e30").is_err());
+        assert!(Curve::parse("exp:0").is_err());
+        assert!(Curve::parse("exp:NaN").is_err());
+        assert!(Curve::parse("exp:inf").is_err());
+    }
+
+    #[test]
+    fn curves_span_zero_to_one() {
+        for curve in [Curve::Linear, Curve::Log(MAX_K), Curve::Exp(MAX_K), Curve::parse(CURVE_PRESETS[3].1).unwrap()] {
+            assert_eq!(curve.apply(0.0), 0.0);
+            assert!((curve.apply(1.0) - 1.0).abs() < 1e-6);
+        }
+        assert_eq!(Curve::Table(vec![(0.0, 0.0), (0.5, 0.1), (1.0, 1.0)]).apply(0.75), 0.55);
+    }
+
+    #[test]
+    fn intervals() {
+        // 换算为整毫秒，忽略 f32 的舍入误差
+        let ms = |curve: &SpeedCurve, percent: f32| curve.interval(percent).map(|d| (d.as_secs_f32() * 1000.0).round());
+
+        let linear = speed_curve(Curve::Linear);
+        assert_eq!(ms(&linear, 4.0), None);
+        assert_eq!(ms(&linear, 100.0), Some(50.0));
+        assert_eq!(ms(&linear, 50.0), Some(80.0));
+        assert_eq!(ms(&speed_curve(Curve::Exp(MAX_K)), 100.0), Some(50.0));
+        // 曲线结果无效时按最慢速度
+        assert_eq!(ms(&speed_curve(Curve::Exp(1000.0)), 100.0), Some(200.0));
+    }
+}
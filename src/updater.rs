@@ -1,56 +1,351 @@
-// CPU监控和动画更新模块：根据CPU使用率更新托盘图标的动画速度
+// 指标监控和动画更新模块：采样线程按固定间隔采样所选指标，动画线程按采样值决定的速度切换帧，
+// 并把包含全部指标读数的快照发送给主线程显示
 
-use std::sync::mpsc;
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
-use std::time::Duration;
-use sysinfo::{CpuExt, System, SystemExt};
-
-use crate::constants::{FRAME_COUNT, IDI_LIGHTCAT_0, IDI_DARKCAT_0};
-
-// 启动CPU监控更新线程
-pub fn start_updater_thread(tx: mpsc::Sender<(u32, f32)>) {
-    thread::spawn(move || {
-        let mut system = System::new_all();
-        let mut icon_index: usize = 0;
-        
-        loop {
-            system.refresh_cpu();
-            
-            let cpu = system.cpus()
-                .iter()
-                .map(|c| c.cpu_usage())
-                .sum::<f32>() / (system.cpus().len() as f32);
-            
-            let cpu_usage = cpu.max(0.0).min(100.0);
-            let duration = calculate_duration(cpu_usage);
-            
-            icon_index = (icon_index + 1) % FRAME_COUNT;
-            
-            if crate::utils::is_skin_follow_system() {
-                if crate::utils::is_time_window_in_dark_mode() {
-                    let res_id = IDI_DARKCAT_0 as u32 + icon_index as u32;
-                    let _ = tx.send((res_id, cpu_usage));
-                } else {
-                    let res_id = IDI_LIGHTCAT_0 as u32 + icon_index as u32;
-                    let _ = tx.send((res_id, cpu_usage));
-                } 
-            } else if crate::utils::is_effective_dark_mode() {
-                let res_id = IDI_DARKCAT_0 as u32 + icon_index as u32;
-                let _ = tx.send((res_id, cpu_usage));
-            } else {
-                let res_id = IDI_LIGHTCAT_0 as u32 + icon_index as u32;
-                let _ = tx.send((res_id, cpu_usage));
-            } 
-            //let res_id = IDI_LIGHTCAT_0 as u32 + icon_index as u32;
-            
-            thread::sleep(duration);
-        }
-    });
-}
-
-// 计算动画帧的切换间隔
-pub fn calculate_duration(cpu_usage: f32) -> Duration {
-    let speed = cpu_usage / 5.0f32;
-    let duration_ms = 500.0f32 / speed.max(0.01f32);
-    Duration::from_millis(duration_ms.clamp(50.0, 200.0) as u64)
+use std::time::{Duration, Instant};
+
+use crate::constants::{IDI_STATUS_OK, IDI_STATUS_FAILED};
+use crate::metrics::{self, Filter, MetricSource, MetricState, TraceRecorder};
+use crate::speed::SpeedCurve;
+use crate::wrapper::WrappedCommand;
+
+// 托盘提示的最大长度（不含结尾的 0）
+const TIP_MAX_CHARS: usize = 127;
+
+// 一个指标的读数
+#[derive(Clone)]
+pub struct MetricReading {
+    pub id: String,
+    pub name: String,
+    pub unit: String,
+    // 经过平滑后的数值，采样失败时为错误原因
+    pub value: Result<f32, String>,
+    // 按取值范围换算后的 0-100，采样失败时为 None
+    pub percent: Option<f32>,
+    pub detail: Option<String>,
+    pub state: MetricState,
+}
+
+// 图标配色
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Theme {
+    Light,
+    Dark,
+}
+
+// 动画线程每切换一帧发送给主线程的快照
+#[derive(Clone)]
+pub struct Snapshot {
+    // 第一个为驱动动画的指标，其余为设置 TooltipMetrics 中附加显示的指标
+    pub metrics: Vec<MetricReading>,
+    pub frame: usize,
+    pub theme: Theme,
+    // 命令包装模式下命令结束后的 (是否成功, 说明)
+    pub finished: Option<(bool, String)>,
+}
+
+impl Snapshot {
+    // 命令结束后显示的状态图标资源ID，命令仍在运行或不在命令包装模式时为 None，显示跑者的当前帧
+    pub fn status_icon(&self) -> Option<u16> {
+        match &self.finished {
+            Some((true, _)) => Some(IDI_STATUS_OK),
+            Some((false, _)) => Some(IDI_STATUS_FAILED),
+            None => None,
+        }
+    }
+
+    // 托盘提示文本：驱动动画的指标及其说明，之后每个附加指标一行；超出长度时截断
+    pub fn tooltip(&self) -> String {
+        if let Some((_, summary)) = &self.finished {
+            return truncate_tip(summary);
+        }
+
+        let mut lines = Vec::new();
+        for (i, reading) in self.metrics.iter().enumerate() {
+            lines.push(match &reading.value {
+                Ok(value) => format!("{}: {:.0}{}", reading.name, value, reading.unit),
+                Err(e) => format!("{}: {}", reading.name, e),
+            });
+            if i == 0 {
+                lines.extend(reading.detail.clone());
+            }
+        }
+        if lines.is_empty() {
+            return "RunCat Rust".to_string();
+        }
+        truncate_tip(&lines.join("\n"))
+    }
+}
+
+fn truncate_tip(text: &str) -> String {
+    let mut tip = String::new();
+    let mut len = 0;
+    for c in text.chars() {
+        len += c.len_utf16();
+        if len > TIP_MAX_CHARS {
+            break;
+        }
+        tip.push(c);
+    }
+    tip
+}
+
+// 采样线程发布的最新结果，动画线程按自己的节奏读取
+#[derive(Clone, Default)]
+struct Latest {
+    metrics: Vec<MetricReading>,
+    finished: Option<(bool, String)>,
+}
+
+// 一个正在采样的指标
+struct Sampled {
+    id: String,
+    source: Box<dyn MetricSource>,
+    filter: Filter,
+    // 创建时读取的指标源设置和滤波设置
+    settings: Vec<String>,
+}
+
+impl Sampled {
+    fn new(id: &str, source: Box<dyn MetricSource>) -> Self {
+        Sampled {
+            id: id.to_string(),
+            source,
+            filter: Filter::from_settings(id),
+            settings: current_settings(id),
+        }
+    }
+
+    // 是否为指定的指标，且创建后相关设置没有变化
+    fn is_current(&self, id: &str) -> bool {
+        self.id == id && self.settings == current_settings(id)
+    }
+
+    // 按设置中的 id 创建，找不到时回退到 CPU
+    fn from_id(id: &str) -> Self {
+        Sampled::new(id, create_selected_source(id))
+    }
+
+    // 采样一次；recorder 为 Some 时同时写入轨迹，driving 表示该指标驱动动画；写入失败时返回错误和本次读数
+    fn read(&mut self, recorder: Option<&mut TraceRecorder>, driving: bool) -> (MetricReading, std::io::Result<()>) {
+        let value = self.source.sample().map(|value| self.filter.apply(value));
+        let recorded = match recorder {
+            Some(recorder) => recorder.record(&self.id, self.source.as_ref(), &value, driving),
+            None => Ok(()),
+        };
+
+        let reading = MetricReading {
+            id: self.id.clone(),
+            name: self.source.name().to_string(),
+            unit: self.source.unit().to_string(),
+            percent: value.as_ref().ok().map(|v| metrics::normalize(*v, self.source.range())),
+            value,
+            detail: self.source.detail(),
+            state: self.source.state(),
+        };
+        (reading, recorded)
+    }
+}
+
+// 启动指标采样线程和动画线程，动画线程向主线程发送快照
+
+// # 参数
+// * `tx`: 消息通道的发送端
+// * `command`: 命令包装模式下运行的命令，为 None 时使用设置中选择的指标源
+// * `recorder`: 轨迹录制器，为 Some 时把驱动动画的指标和附加显示的指标的每次采样结果写入轨迹文件
+// * `linger`: 命令结束后保留结果的时长，之后动画线程退出，主消息循环随之结束
+pub fn start_updater_thread(
+    tx: mpsc::Sender<Snapshot>,
+    command: Option<WrappedCommand>,
+    recorder: Option<TraceRecorder>,
+    linger: Duration,
+) {
+    let latest = Arc::new(Mutex::new(Latest::default()));
+
+    let shared = Arc::clone(&latest);
+    thread::spawn(move || run_sampler(shared, command, recorder));
+    thread::spawn(move || run_animation(latest, tx, linger));
+}
+
+// 采样线程：按设置的固定间隔采样，与动画帧率无关
+fn run_sampler(latest: Arc<Mutex<Latest>>, mut command: Option<WrappedCommand>, mut recorder: Option<TraceRecorder>) {
+    let mut generation = crate::utils::settings_generation();
+    let mut primary = match &command {
+        Some(command) => Sampled::new(metrics::METRIC_PROCESS, Box::new(command.source())),
+        None => Sampled::from_id(&crate::utils::get_metric_source()),
+    };
+    let mut extras = update_extra_metrics(Vec::new(), &primary.id);
+
+    // 动画线程退出后采样线程随之结束
+    while Arc::strong_count(&latest) > 1 {
+        if let Some(command) = command.as_mut() {
+            if let Some(result) = command.try_finish() {
+                latest.lock().unwrap().finished = Some(result);
+                return;
+            }
+        }
+        if crate::utils::settings_generation() != generation {
+            // 设置发生变化时只重新创建相关设置有变化的指标，其余指标保留滤波状态、按差值计算的计数器、
+            // 命令退避和速率历史；跑者、速度曲线、数值叠加等设置不影响指标。命令包装模式下始终跟踪命令
+            generation = crate::utils::settings_generation();
+            if command.is_none() {
+                let id = crate::utils::get_metric_source();
+                if !primary.is_current(&id) {
+                    primary = Sampled::from_id(&id);
+                }
+            }
+            extras = update_extra_metrics(extras, &primary.id);
+        }
+
+        let (reading, mut recorded) = primary.read(recorder.as_mut(), true);
+        let mut readings = vec![reading];
+        for extra in extras.iter_mut() {
+            let (reading, result) = extra.read(recorder.as_mut(), false);
+            readings.push(reading);
+            recorded = recorded.and(result);
+        }
+        if let Err(e) = recorded {
+            eprintln!("轨迹录制失败，已停止录制: {}", e);
+            recorder = None;
+        }
+
+        latest.lock().unwrap().metrics = readings;
+
+        wait_for_next_sample(generation);
+    }
+}
+
+// 按设置更新附加显示的指标：保留 id 和相关设置都没有变化的指标，其余重新创建；
+// 跳过驱动动画的指标和无法识别的 id
+fn update_extra_metrics(mut previous: Vec<Sampled>, primary_id: &str) -> Vec<Sampled> {
+    crate::utils::get_tooltip_metrics()
+        .iter()
+        .filter(|id| id.as_str() != primary_id)
+        .filter_map(|id| match previous.iter().position(|extra| extra.is_current(id)) {
+            Some(index) => Some(previous.swap_remove(index)),
+            None => metrics::create_source(id).map(|source| Sampled::new(id, source)),
+        })
+        .collect()
+}
+
+// 创建指标时读取的设置值：指标源的设置和该指标的平滑滤波
+fn current_settings(id: &str) -> Vec<String> {
+    let mut settings = metrics::source_settings(id);
+    settings.push(crate::utils::get_metric_filter(id));
+    settings
+}
+
+// 等待一个采样间隔；期间设置发生变化时提前结束，使新设置尽快生效
+fn wait_for_next_sample(generation: u32) {
+    let interval = Duration::from_millis(crate::utils::get_sample_interval_ms() as u64);
+    let started = Instant::now();
+    while started.elapsed() < interval && crate::utils::settings_generation() == generation {
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+// 动画线程：按速度曲线把最新采样值换算成帧间隔切换帧
+fn run_animation(latest: Arc<Mutex<Latest>>, tx: mpsc::Sender<Snapshot>, linger: Duration) {
+    let mut frame: usize = 0;
+    let mut generation = crate::utils::settings_generation();
+    let mut curve = SpeedCurve::from_settings();
+
+    loop {
+        let current = latest.lock().unwrap().clone();
+        let theme = current_theme();
+
+        if current.finished.is_some() {
+            let _ = tx.send(Snapshot {
+                metrics: current.metrics,
+                frame,
+                theme,
+                finished: current.finished,
+            });
+            thread::sleep(linger);
+            return;
+        }
+
+        // 帧数和各帧时长随当前跑者变化，切换跑者后帧序号按新的帧数取模
+        let weights = crate::runner::frame_weights();
+        let count = weights.len();
+        frame %= count;
+
+        if crate::utils::settings_generation() != generation {
+            generation = crate::utils::settings_generation();
+            curve = SpeedCurve::from_settings();
+        }
+
+        // 驱动动画的指标采样失败或低于停止阈值时小猫停下
+        let primary = current.metrics.first();
+        let interval = primary.and_then(|reading| reading.percent).and_then(|percent| curve.interval(percent));
+        if interval.is_some() {
+            frame = match primary.map(|reading| reading.state) {
+                Some(MetricState::Charging) => (frame + count - 1) % count,
+                _ => (frame + 1) % count,
+            };
+        }
+
+        let snapshot = Snapshot {
+            metrics: current.metrics,
+            frame,
+            theme,
+            finished: None,
+        };
+        if tx.send(snapshot).is_err() {
+            return;
+        }
+
+        thread::sleep(match interval {
+            Some(interval) => frame_duration(interval, &weights, frame),
+            None => curve.idle_interval(),
+        });
+    }
+}
+
+// 指定帧的显示时长：按各帧的相对时长分配，各帧的平均时长等于速度曲线给出的间隔
+// 清单中的时长已检查为正数，但总和仍可能溢出为无穷大，此时各帧时长相同
+fn frame_duration(interval: Duration, weights: &[f32], frame: usize) -> Duration {
+    let total: f32 = weights.iter().sum();
+    let scale = weights[frame] * weights.len() as f32 / total;
+    if scale.is_finite() && scale >= 0.0 {
+        interval.mul_f32(scale)
+    } else {
+        interval
+    }
+}
+
+// 创建设置中选择的指标源，找不到时回退到 CPU
+fn create_selected_source(id: &str) -> Box<dyn MetricSource> {
+    metrics::create_source(id).unwrap_or_else(|| {
+        eprintln!("未知的指标源: {}，使用 CPU", id);
+        Box::new(metrics::CpuSource::from_settings())
+    })
+}
+
+// 根据当前颜色模式返回图标配色
+fn current_theme() -> Theme {
+    if crate::utils::is_effective_dark_mode() {
+        Theme::Dark
+    } else {
+        Theme::Light
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_duration_follows_weights() {
+        let interval = Duration::from_secs(2);
+        assert_eq!(frame_duration(interval, &[1.0, 1.0, 2.0], 2), Duration::from_secs(3));
+        assert_eq!(frame_duration(interval, &[1.0, 1.0, 2.0], 0), Duration::from_millis(1500));
+    }
+
+    #[test]
+    fn overflowing_weights_fall_back_to_interval() {
+        let interval = Duration::from_millis(100);
+        assert_eq!(frame_duration(interval, &[f32::MAX, f32::MAX], 0), interval);
+    }
 }
@@ -6,12 +6,14 @@ use winapi::um::winuser::{LoadIconW, LoadCursorW, IDC_ARROW};
 use winapi::um::libloaderapi::GetModuleHandleW;
 use winapi::um::minwinbase::SYSTEMTIME;
 use winapi::um::sysinfoapi::GetLocalTime;
+use winapi::um::wincon::{AttachConsole, ATTACH_PARENT_PROCESS};
 use winapi::shared::minwindef::{HINSTANCE, WORD};
 use winapi::shared::windef::HICON;
 use std::ptr::null_mut;
 use winreg::RegKey;
 use winreg::enums::*;
 use std::env;
+use std::sync::atomic::{AtomicU32, Ordering};
 
 // Windows宽字符字符串
 pub fn to_wide_null(s: impl AsRef<str>) -> Vec<u16> {
@@ -22,10 +24,11 @@ pub fn to_wide_null(s: impl AsRef<str>) -> Vec<u16> {
     wide
 }
 
-// 加载图标资源
+// 加载图标资源，32512 及以上的ID为系统预定义图标
 pub fn load_icon(hinstance: HINSTANCE, res_id: u16) -> HICON {
+    let module = if res_id >= 32512 { null_mut() } else { hinstance };
     unsafe {
-        LoadIconW(hinstance, res_id as WORD as usize as *const u16)
+        LoadIconW(module, res_id as WORD as usize as *const u16)
     }
 }
 
@@ -36,6 +39,13 @@ pub fn load_cursor() -> HICON {
     }
 }
 
+// 附加到启动本程序的终端，使命令行输出和子进程输出显示在该终端中
+pub fn attach_parent_console() {
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
 // 获取当前模块的句柄
 
 pub fn get_module_handle() -> HINSTANCE {
@@ -232,3 +242,288 @@ pub fn is_effective_dark_mode() -> bool {
         is_app_force_dark()
     }
 }
+
+
+
+// 设置版本号，每次通过本模块写入设置时递增，后台线程据此判断是否需要重新加载
+static SETTINGS_GENERATION: AtomicU32 = AtomicU32::new(0);
+
+pub fn settings_generation() -> u32 {
+    SETTINGS_GENERATION.load(Ordering::SeqCst)
+}
+
+// 以写权限打开 RunCat 设置子键，不存在时自动创建
+fn open_settings_key_for_write() -> Option<RegKey> {
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    match hkcu.open_subkey_with_flags(RUNCAT_SETTINGS_SUBKEY, KEY_WRITE) {
+        Ok(key) => Some(key),
+        Err(_) => match hkcu.create_subkey(RUNCAT_SETTINGS_SUBKEY) {
+            Ok((key, _)) => Some(key),
+            Err(e) => {
+                eprintln!("无法创建 RunCat 设置子键: {}", e);
+                None
+            }
+        },
+    }
+}
+
+// 读取字符串设置项，不存在时返回默认值
+pub fn get_setting_string(name: &str, default: &str) -> String {
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    match hkcu.open_subkey_with_flags(RUNCAT_SETTINGS_SUBKEY, KEY_READ) {
+        Ok(key) => key.get_value::<String, _>(name).unwrap_or_else(|_| default.to_string()),
+        Err(_) => default.to_string(),
+    }
+}
+
+// 写入字符串设置项
+pub fn set_setting_string(name: &str, value: &str) {
+    if let Some(key) = open_settings_key_for_write() {
+        if let Err(e) = key.set_value(name, &value) {
+            eprintln!("写入 {} 失败: {}", name, e);
+        }
+    }
+    SETTINGS_GENERATION.fetch_add(1, Ordering::SeqCst);
+}
+
+// 读取整数设置项，不存在时返回默认值
+pub fn get_setting_u32(name: &str, default: u32) -> u32 {
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    match hkcu.open_subkey_with_flags(RUNCAT_SETTINGS_SUBKEY, KEY_READ) {
+        Ok(key) => key.get_value::<u32, _>(name).unwrap_or(default),
+        Err(_) => default,
+    }
+}
+
+// 读取以字符串保存的小数设置项，以支持负数和小数；不存在或无法解析时返回默认值
+pub fn get_setting_f32(name: &str, default: f32) -> f32 {
+    get_setting_string(name, "")
+        .trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|v| v.is_finite())
+        .unwrap_or(default)
+}
+
+/// 当前选择的指标源 id（默认 CPU 平均占用）
+pub fn get_metric_source() -> String {
+    get_setting_string("MetricSource", crate::metrics::METRIC_CPU)
+}
+
+pub fn set_metric_source(id: &str) {
+    set_setting_string("MetricSource", id);
+}
+
+// CPU 统计方式：average、max、top:N、core:N
+pub fn get_cpu_mode() -> String {
+    get_setting_string("CpuMode", "average")
+}
+
+// 网络指标统计的接口名称，空字符串表示全部接口
+pub fn get_net_interface() -> String {
+    get_setting_string("NetInterface", "")
+}
+
+// 网络吞吐量上限（Mbit/s），达到该值时猫咪全速奔跑
+pub fn get_net_ceiling_mbps() -> u32 {
+    get_setting_u32("NetCeilingMbps", 100)
+}
+
+// 磁盘指标统计的设备名称，空字符串表示任意磁盘
+pub fn get_disk_device() -> String {
+    get_setting_string("DiskDevice", "")
+}
+
+// PSI 指标使用的行和平均窗口，例如 some:avg10、full:avg60
+pub fn get_psi_window() -> String {
+    get_setting_string("PsiWindow", "some:avg10")
+}
+
+// cgroup v2 指标使用的 cgroup 路径，空字符串表示自动检测当前进程所在的 cgroup
+pub fn get_cgroup_path() -> String {
+    get_setting_string("CgroupPath", "")
+}
+
+// 进程指标跟踪的目标：pid:1234、name:<正则表达式>、tree:1234
+pub fn get_process_target() -> String {
+    get_setting_string("ProcessTarget", "")
+}
+
+// 温度指标使用的传感器名称，空字符串表示最热的传感器
+pub fn get_thermal_sensor() -> String {
+    get_setting_string("ThermalSensor", "")
+}
+
+// 温度区间下限（°C），低于该温度时小猫最慢
+pub fn get_thermal_min_c() -> u32 {
+    get_setting_u32("ThermalMinC", 40)
+}
+
+// 温度区间上限（°C），达到该温度（或传感器的临界温度）时小猫全速
+pub fn get_thermal_max_c() -> u32 {
+    get_setting_u32("ThermalMaxC", 95)
+}
+
+// 放电功率上限（瓦），达到该值时小猫全速
+pub fn get_battery_max_watts() -> u32 {
+    get_setting_u32("BatteryMaxWatts", 30)
+}
+
+// 负载均值窗口（分钟）：1、5 或 15；菜单以字符串写入
+pub fn get_load_window() -> u32 {
+    get_setting_string("LoadWindow", "1").parse().unwrap_or(1)
+}
+
+// 负载百分比上限，负载除以核心数达到该百分比时小猫全速
+pub fn get_load_ceiling_percent() -> u32 {
+    get_setting_u32("LoadCeilingPercent", 200)
+}
+
+// 指标平滑滤波的设置项名称，每个指标单独保存
+pub fn metric_filter_setting(metric_id: &str) -> String {
+    format!("Filter.{}", metric_id)
+}
+
+// 指标的平滑滤波描述，例如 ema:0.3、median:5,deadband:3；默认不滤波
+pub fn get_metric_filter(metric_id: &str) -> String {
+    get_setting_string(&metric_filter_setting(metric_id), "none")
+}
+
+// Prometheus 指标抓取的地址
+pub fn get_prom_url() -> String {
+    get_setting_string("PromUrl", "http://127.0.0.1:9100/metrics")
+}
+
+// Prometheus 序列选择器，如 node_load1 或 rate(node_network_receive_bytes_total{device="eth0"})
+pub fn get_prom_query() -> String {
+    get_setting_string("PromQuery", "node_load1")
+}
+
+// Prometheus 序列值映射到最慢的下限
+pub fn get_prom_min() -> f32 {
+    get_setting_f32("PromMin", 0.0)
+}
+
+// Prometheus 序列值映射到全速的上限
+pub fn get_prom_max() -> f32 {
+    get_setting_f32("PromMax", 100.0)
+}
+
+// 推送指标监听的地址：Linux 为 UNIX 域套接字路径，Windows 为命名管道名，空表示默认地址
+pub fn get_push_address() -> String {
+    match get_setting_string("PushAddress", "") {
+        address if address.trim().is_empty() => crate::metrics::default_push_address(),
+        address => address.trim().to_string(),
+    }
+}
+
+// 推送数值未指定有效期时的默认有效期（秒），0 表示永不过期
+pub fn get_push_ttl_secs() -> u32 {
+    get_setting_u32("PushTtlSecs", 60)
+}
+
+// 轨迹回放使用的文件
+pub fn get_replay_path() -> String {
+    get_setting_string("ReplayPath", "")
+}
+
+// 轨迹回放速度倍数
+pub fn get_replay_speed() -> f32 {
+    get_setting_string("ReplaySpeed", "1")
+        .trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|v| v.is_finite() && *v > 0.0)
+        .unwrap_or(1.0)
+}
+
+// 综合指标的表达式
+pub fn get_composite_expr() -> String {
+    get_setting_string("CompositeExpr", "max(cpu, mem)")
+}
+
+// 外部命令指标运行的命令
+pub fn get_command_line() -> String {
+    get_setting_string("CommandLine", "")
+}
+
+// 外部命令输出的解析方式：number、key:名称 或 json:路径
+pub fn get_command_parse() -> String {
+    get_setting_string("CommandParse", "number")
+}
+
+// 外部命令输出值映射到最慢的下限
+pub fn get_command_min() -> f32 {
+    get_setting_f32("CommandMin", 0.0)
+}
+
+// 外部命令输出值映射到全速的上限
+pub fn get_command_max() -> f32 {
+    get_setting_f32("CommandMax", 100.0)
+}
+
+// 外部命令的超时时间（毫秒）
+pub fn get_command_timeout_ms() -> u32 {
+    get_setting_u32("CommandTimeoutMs", 5000).max(100)
+}
+
+// 在托盘提示中附加显示的指标 id，设置中以逗号分隔
+pub fn get_tooltip_metrics() -> Vec<String> {
+    get_setting_string("TooltipMetrics", "")
+        .split(',')
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+        .collect()
+}
+
+// 指标采样间隔（毫秒），与动画帧率无关；限制在 200 毫秒到 1 分钟之间
+pub fn get_sample_interval_ms() -> u32 {
+    get_setting_u32("SampleIntervalMs", 1000).clamp(200, 60_000)
+}
+
+// 使用的跑者包：空为内置小猫，否则为跑者包目录中的目录名或 zip 文件名，也可为绝对路径
+pub fn get_runner_pack() -> String {
+    get_setting_string("RunnerPack", "").trim().to_string()
+}
+
+pub fn set_runner_pack(pack: &str) {
+    set_setting_string("RunnerPack", pack);
+}
+
+// 指标值到动画速度的曲线：linear、log[:k]、exp[:k] 或 table:指标值=速度,...
+pub fn get_speed_curve() -> String {
+    get_setting_string("SpeedCurve", "linear").trim().to_string()
+}
+
+// 动画帧间隔 (最快, 最慢)（毫秒）；限制在 10 毫秒到 5 秒之间，最慢不小于最快
+pub fn get_frame_interval_ms() -> (u32, u32) {
+    let min_ms = get_setting_u32("FrameMinMs", 50).clamp(10, 5000);
+    let max_ms = get_setting_u32("FrameMaxMs", 200).clamp(10, 5000);
+    (min_ms, max_ms.max(min_ms))
+}
+
+// 指标值低于该百分比时跑者停下，0 表示不停下
+pub fn get_stop_below_percent() -> f32 {
+    get_setting_string("StopBelowPercent", "0")
+        .trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|v| v.is_finite())
+        .unwrap_or(0.0)
+        .clamp(0.0, 100.0)
+}
+
+// 图标上叠加指标值的位置：off、top-left、top-right、bottom-left、bottom-right、center
+pub fn get_value_overlay() -> String {
+    get_setting_string("ValueOverlay", "off").trim().to_string()
+}
+
+// 叠加数值与图标的对比方式：outline、backdrop、none
+pub fn get_overlay_contrast() -> String {
+    get_setting_string("OverlayContrast", "outline").trim().to_string()
+}
+
+// 叠加数值的取整宽度，显示的数值取整到它的倍数
+pub fn get_overlay_bucket() -> u32 {
+    get_setting_u32("OverlayBucket", 1).clamp(1, 100)
+}
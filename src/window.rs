@@ -2,14 +2,41 @@
 
 use winapi::shared::minwindef::{HINSTANCE, LRESULT, UINT, WPARAM, LPARAM};
 use winapi::shared::basetsd::UINT_PTR;
-use winapi::shared::windef::HWND;
+use winapi::shared::windef::{HWND, HMENU};
 use winapi::um::winuser::*;
 use winapi::shared::windef::POINT;
 use std::ptr::null_mut;
+use std::cell::RefCell;
 
-use crate::constants::{WM_TRAYICON, IDM_EXIT, IDM_START_SYSTEM, IDM_SHOW_TIME, IDM_SKIN_DARK, IDM_SKIN_LIGHT, IDM_SKIN_AUTO};
+use crate::constants::{WM_TRAYICON, IDM_EXIT, IDM_START_SYSTEM, IDM_SHOW_TIME, IDM_SKIN_DARK, IDM_SKIN_LIGHT, IDM_SKIN_AUTO, IDM_METRIC_BASE, IDM_METRIC_LAST, IDM_OPTION_BASE, IDM_OPTION_LAST, IDM_RUNNER_BASE, IDM_RUNNER_LAST, IDM_RUNNER_RELOAD, MAX_OPTION_CHOICES};
 use crate::utils::{to_wide_null, load_cursor, load_icon};
+use crate::updater::{MetricReading, Snapshot};
 
+thread_local! {
+    // 弹出菜单时列出的指标选项 (设置项名称, 设置值)，菜单命令按下标取回
+    static MENU_OPTIONS: RefCell<Vec<(String, String)>> = const { RefCell::new(Vec::new()) };
+    // 弹出菜单时列出的指标源 id；推送指标会随时过期，菜单命令不能重新查询列表
+    static MENU_METRICS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    // 弹出菜单时列出的跑者包设置值，空字符串为内置小猫
+    static MENU_RUNNERS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    // 最近一次快照中的指标读数，菜单中在指标名称后显示当前值
+    static LATEST_READINGS: RefCell<Vec<MetricReading>> = const { RefCell::new(Vec::new()) };
+}
+
+// 保存主线程收到的最新快照，供弹出菜单显示当前值
+pub fn set_latest_snapshot(snapshot: &Snapshot) {
+    LATEST_READINGS.with(|readings| *readings.borrow_mut() = snapshot.metrics.clone());
+}
+
+// 指定指标在最近一次快照中的数值，没有采样该指标或采样失败时返回 None
+fn live_value(id: &str) -> Option<String> {
+    LATEST_READINGS.with(|readings| {
+        let readings = readings.borrow();
+        let reading = readings.iter().find(|r| r.id == id)?;
+        let value = reading.value.as_ref().ok()?;
+        Some(format!("{:.0}{}", value, reading.unit))
+    })
+}
 
 // 注册窗口类并创建窗口
 pub fn register_class_and_create_window(hinstance: HINSTANCE, class_name: *const u16) -> Result<(), &'static str> {
@@ -140,6 +167,37 @@ unsafe fn handle_tray_icon_message(hwnd: HWND, lparam: LPARAM) -> LRESULT {
                 light_text.as_ptr(),
             );
 
+            // 指标源子菜单，勾选当前使用的指标
+            let hsubmenu_metric = CreatePopupMenu();
+            let selected_metric = crate::utils::get_metric_source();
+            let metric_slots = (IDM_METRIC_LAST - IDM_METRIC_BASE + 1) as usize;
+            let metrics: Vec<_> = crate::metrics::available_metrics().into_iter().take(metric_slots).collect();
+            MENU_METRICS.with(|menu| *menu.borrow_mut() = metrics.iter().map(|info| info.id.clone()).collect());
+            for (i, info) in metrics.iter().enumerate() {
+                let mut flags = MF_STRING;
+                if info.id == selected_metric {
+                    flags |= MF_CHECKED;
+                }
+                let label = match live_value(&info.id) {
+                    Some(value) => format!("{}\t{}", info.name, value),
+                    None => info.name.clone(),
+                };
+                AppendMenuW(
+                    hsubmenu_metric,
+                    flags,
+                    (IDM_METRIC_BASE as usize + i) as UINT_PTR,
+                    to_wide_null(&label).as_ptr(),
+                );
+            }
+
+            AppendMenuW(
+                hmenu,
+                MF_POPUP,
+                hsubmenu_metric as UINT_PTR,
+                to_wide_null("监控指标").as_ptr(),
+            );
+            append_metric_options(hmenu, &selected_metric);
+
             // 将子菜单添加到主弹出菜单
             AppendMenuW(
                 hmenu,
@@ -147,6 +205,7 @@ unsafe fn handle_tray_icon_message(hwnd: HWND, lparam: LPARAM) -> LRESULT {
                 hsubmenu_skin as UINT_PTR,
                 to_wide_null("颜色模式").as_ptr(),
             );
+            append_runner_menu(hmenu);
             // 添加"显示时间"或"隐藏时间"菜单项到菜单
             AppendMenuW(
                 hmenu,
@@ -196,6 +255,76 @@ unsafe fn handle_tray_icon_message(hwnd: HWND, lparam: LPARAM) -> LRESULT {
     0
 }
 
+// 添加当前指标源的选项子菜单，以及与指标无关的速度曲线和图标数值选项。
+// 每个子菜单最多列出 MAX_OPTION_CHOICES 项，未列出的选项数显示在子菜单末尾，其后的子菜单不受影响
+unsafe fn append_metric_options(hmenu: HMENU, selected_metric: &str) {
+    let slots = (IDM_OPTION_LAST - IDM_OPTION_BASE + 1) as usize;
+    let mut items: Vec<(String, String)> = Vec::new();
+
+    for options in crate::metrics::metric_options(selected_metric)
+        .into_iter()
+        .chain(crate::speed::speed_options())
+        .chain(crate::runner::overlay_options()) {
+        let hsubmenu = CreatePopupMenu();
+        let limit = MAX_OPTION_CHOICES.min(slots - items.len());
+        let hidden = options.choices.len().saturating_sub(limit);
+        for (label, value) in options.choices.into_iter().take(limit) {
+            let mut flags = MF_STRING;
+            if value == options.current {
+                flags |= MF_CHECKED;
+            }
+            AppendMenuW(
+                hsubmenu,
+                flags,
+                (IDM_OPTION_BASE as usize + items.len()) as UINT_PTR,
+                to_wide_null(&label).as_ptr(),
+            );
+            items.push((options.setting.clone(), value));
+        }
+        if hidden > 0 {
+            let note = format!("另有 {} 项未列出，可在设置 {} 中指定", hidden, options.setting);
+            AppendMenuW(hsubmenu, MF_STRING | MF_GRAYED, 0, to_wide_null(&note).as_ptr());
+        }
+        AppendMenuW(hmenu, MF_POPUP, hsubmenu as UINT_PTR, to_wide_null(options.title).as_ptr());
+    }
+
+    MENU_OPTIONS.with(|menu| *menu.borrow_mut() = items);
+}
+
+// 添加跑者子菜单：内置小猫和跑者包目录中的全部跑者包，勾选当前使用的跑者
+unsafe fn append_runner_menu(hmenu: HMENU) {
+    let hsubmenu = CreatePopupMenu();
+    let selected = crate::utils::get_runner_pack();
+    let slots = (IDM_RUNNER_LAST - IDM_RUNNER_BASE + 1) as usize;
+
+    let mut items = vec![(String::new(), "内置小猫".to_string())];
+    items.extend(crate::runner::list_packs().into_iter().map(|pack| (pack.id, pack.name)));
+    items.truncate(slots);
+
+    for (i, (id, name)) in items.iter().enumerate() {
+        let mut flags = MF_STRING;
+        if *id == selected {
+            flags |= MF_CHECKED;
+        }
+        AppendMenuW(
+            hsubmenu,
+            flags,
+            (IDM_RUNNER_BASE as usize + i) as UINT_PTR,
+            to_wide_null(name).as_ptr(),
+        );
+    }
+    AppendMenuW(hsubmenu, MF_SEPARATOR, 0, null_mut());
+    AppendMenuW(
+        hsubmenu,
+        MF_STRING,
+        IDM_RUNNER_RELOAD as UINT_PTR,
+        to_wide_null("重新加载跑者包").as_ptr(),
+    );
+    AppendMenuW(hmenu, MF_POPUP, hsubmenu as UINT_PTR, to_wide_null("跑者").as_ptr());
+
+    MENU_RUNNERS.with(|menu| *menu.borrow_mut() = items.into_iter().map(|(id, _)| id).collect());
+}
+
 // 处理命令消息
 unsafe fn handle_command_message(wparam: WPARAM) -> LRESULT {
     match wparam as UINT {
@@ -257,6 +386,31 @@ unsafe fn handle_command_message(wparam: WPARAM) -> LRESULT {
         IDM_EXIT => {
             PostQuitMessage(0);
         }
+
+        IDM_RUNNER_RELOAD => {
+            crate::runner::request_reload();
+        }
+
+        id if (IDM_RUNNER_BASE..=IDM_RUNNER_LAST).contains(&id) => {
+            let pack = MENU_RUNNERS.with(|menu| menu.borrow().get((id - IDM_RUNNER_BASE) as usize).cloned());
+            if let Some(pack) = pack {
+                crate::utils::set_runner_pack(&pack);
+            }
+        }
+
+        id if (IDM_METRIC_BASE..=IDM_METRIC_LAST).contains(&id) => {
+            let metric = MENU_METRICS.with(|menu| menu.borrow().get((id - IDM_METRIC_BASE) as usize).cloned());
+            if let Some(metric) = metric {
+                crate::utils::set_metric_source(&metric);
+            }
+        }
+
+        id if (IDM_OPTION_BASE..=IDM_OPTION_LAST).contains(&id) => {
+            let item = MENU_OPTIONS.with(|menu| menu.borrow().get((id - IDM_OPTION_BASE) as usize).cloned());
+            if let Some((setting, value)) = item {
+                crate::utils::set_setting_string(&setting, &value);
+            }
+        }
         
         _ => {}
     }
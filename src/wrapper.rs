@@ -0,0 +1,122 @@
+// 命令包装模式：启动子进程，按子进程树的 CPU 占用驱动小猫，结束后显示结果并以子进程的退出码退出
+
+use std::process::{Child, Command};
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::metrics::{ProcessSource, ProcessTarget};
+use crate::process_tree::ProcessTree;
+
+// 提示文本中命令的最大显示长度
+const MAX_LABEL_CHARS: usize = 40;
+
+// 命令运行中 RunCat 被退出时使用的退出码，与在终端中按 Ctrl+C 中断命令一致
+const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+// RunCat 退出时使用的退出码，命令结束后设置为子进程的退出码
+static EXIT_CODE: AtomicI32 = AtomicI32::new(0);
+
+// 仍在运行的子进程及其进程树。放在全局而不是 WrappedCommand 中，使退出流程在更新线程之外也能结束它
+static RUNNING: Mutex<Option<(Child, ProcessTree)>> = Mutex::new(None);
+
+pub fn exit_code() -> i32 {
+    EXIT_CODE.load(Ordering::SeqCst)
+}
+
+// 结束仍在运行的子进程及其启动的全部进程并等待子进程退出，退出码记为被中断；RunCat 退出前调用
+pub fn terminate() {
+    let mut running = RUNNING.lock().unwrap();
+    if let Some((mut child, tree)) = running.take() {
+        // 子进程可能已经结束，只是更新线程还没来得及读取状态
+        if let Ok(Some(status)) = child.try_wait() {
+            EXIT_CODE.store(status.code().unwrap_or(1), Ordering::SeqCst);
+            return;
+        }
+        tree.kill(&mut child);
+        let _ = child.wait();
+        EXIT_CODE.store(INTERRUPTED_EXIT_CODE, Ordering::SeqCst);
+    }
+}
+
+pub struct WrappedCommand {
+    pid: u32,
+    started: Instant,
+    label: String,
+}
+
+impl WrappedCommand {
+    // 启动命令，子进程继承当前的标准输入输出
+    pub fn spawn(args: &[String]) -> Result<Self, String> {
+        let (program, rest) = args.split_first().ok_or_else(|| "未指定要运行的命令".to_string())?;
+        let (child, tree) = ProcessTree::spawn(Command::new(program).args(rest))
+            .map_err(|e| format!("无法启动 {}: {}", program, e))?;
+
+        let mut label = args.join(" ");
+        if label.chars().count() > MAX_LABEL_CHARS {
+            label = label.chars().take(MAX_LABEL_CHARS - 1).collect::<String>() + "…";
+        }
+
+        let pid = child.id();
+        *RUNNING.lock().unwrap() = Some((child, tree));
+
+        Ok(WrappedCommand {
+            pid,
+            started: Instant::now(),
+            label,
+        })
+    }
+
+    // 跟踪子进程及其全部子孙进程的指标源
+    pub fn source(&self) -> ProcessSource {
+        ProcessSource::new(Ok(ProcessTarget::Tree(self.pid)))
+    }
+
+    // 子进程结束时返回 (是否成功, 提示文本)，并记录退出码；仍在运行时返回 None
+    pub fn try_finish(&mut self) -> Option<(bool, String)> {
+        let mut running = RUNNING.lock().unwrap();
+        // 已被 terminate 结束
+        let (child, _) = running.as_mut()?;
+        let status = match child.try_wait() {
+            Ok(Some(status)) => status,
+            Ok(None) => return None,
+            Err(e) => {
+                *running = None;
+                EXIT_CODE.store(1, Ordering::SeqCst);
+                return Some((false, format!("{}\n无法获取退出状态: {}", self.label, e)));
+            }
+        };
+        *running = None;
+
+        // 被信号终止时没有退出码
+        let code = status.code().unwrap_or(1);
+        EXIT_CODE.store(code, Ordering::SeqCst);
+
+        let elapsed = format_elapsed(self.started.elapsed());
+        let tip = if status.success() {
+            format!("{}\n成功，用时 {}", self.label, elapsed)
+        } else {
+            format!("{}\n失败（退出码 {}），用时 {}", self.label, code, elapsed)
+        };
+        Some((status.success(), tip))
+    }
+}
+
+impl Drop for WrappedCommand {
+    // 命令还没结束就不再跟踪时（例如更新线程退出），不留下无人等待的子进程
+    fn drop(&mut self) {
+        terminate();
+    }
+}
+
+// 格式化耗时，例如 "12.3 秒"、"3 分 05 秒"、"1 时 02 分 03 秒"
+fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    if secs < 60 {
+        format!("{:.1} 秒", elapsed.as_secs_f32())
+    } else if secs < 3600 {
+        format!("{} 分 {:02} 秒", secs / 60, secs % 60)
+    } else {
+        format!("{} 时 {:02} 分 {:02} 秒", secs / 3600, secs % 3600 / 60, secs % 60)
+    }
+}